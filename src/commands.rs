@@ -1,7 +1,30 @@
+mod config;
+mod guide;
+mod invites;
+mod note;
 mod onboarding;
+mod panel;
 mod ping;
+mod pronouns;
+mod rolegroup;
+mod role_selection;
+mod rolemenu;
 mod setup;
+mod temprole;
+mod vouch;
+mod welcome;
 
+pub use config::config;
+pub use guide::guide;
+pub use invites::invites;
+pub use note::note;
 pub use onboarding::onboarding;
+pub use panel::panel;
 pub use ping::ping;
+pub use pronouns::{pronouns, show_pronouns};
+pub use rolegroup::rolegroup;
+pub use rolemenu::rolemenu;
 pub use setup::setup;
+pub use temprole::temprole;
+pub use vouch::vouch;
+pub use welcome::welcome;
@@ -0,0 +1,476 @@
+use redis::Commands;
+use serenity::model::channel::{Channel, ChannelType};
+use serenity::model::guild::Role;
+use serenity::model::id::{ChannelId, RoleId};
+use serenity::prelude::Mentionable;
+use serenity::utils::Colour;
+use tracing::warn;
+
+use crate::{Context, Error};
+use crate::{messages, utils};
+use crate::models::{GuildConfiguration, OnboardingMode, PersistentMessage};
+
+/// Change or review individual settings without running /setup again
+#[poise::command(
+    slash_command,
+    subcommands("set", "show", "check"),
+)]
+pub async fn config(_: Context<'_>) -> Result<(), Error> {
+    Ok(())
+}
+
+/// Change a single setting
+#[poise::command(
+    slash_command,
+    subcommands("admin_role", "validated_role", "introductions_channel", "role_assignment_channel", "validation_category", "welcome_channel", "log_channel", "notify_role", "mode", "threads_channel", "channel_name"),
+)]
+async fn set(_: Context<'_>) -> Result<(), Error> {
+    Ok(())
+}
+
+/// Set the role allowed to run restricted commands
+#[poise::command(slash_command)]
+async fn admin_role(
+    ctx: Context<'_>,
+    #[description = "The role that is allowed to run restricted commands."] role: Role,
+) -> Result<(), Error> {
+    let mut database = match utils::admin_database(ctx).await? {
+        Some(database) => database,
+        None => return Ok(()),
+    };
+    let guild_id = ctx.guild_id().unwrap();
+    let guild_key = format!("guild:{}", guild_id);
+
+    if let Some(problem) = utils::role_problem("admin role", &role, utils::bot_position(ctx).await?, false) {
+        poise::send_reply(ctx, |reply| {
+            reply
+                .content(problem)
+                .ephemeral(true)
+        }).await?;
+
+        return Ok(());
+    }
+
+    let _: () = database.hset(&guild_key, "admin_role", role.id.as_u64())?;
+
+    ctx.say(format!("✅ Set {} as the role allowed to run restricted commands", role)).await?;
+
+    Ok(())
+}
+
+/// Set the role granted to approved members
+#[poise::command(slash_command)]
+async fn validated_role(
+    ctx: Context<'_>,
+    #[description = "The role to grant upon validation of a member."] role: Role,
+) -> Result<(), Error> {
+    let mut database = match utils::admin_database(ctx).await? {
+        Some(database) => database,
+        None => return Ok(()),
+    };
+    let guild_id = ctx.guild_id().unwrap();
+    let guild_key = format!("guild:{}", guild_id);
+
+    if let Some(problem) = utils::role_problem("validated role", &role, utils::bot_position(ctx).await?, true) {
+        poise::send_reply(ctx, |reply| {
+            reply
+                .content(problem)
+                .ephemeral(true)
+        }).await?;
+
+        return Ok(());
+    }
+
+    let _: () = database.hset(&guild_key, "validated_role", role.id.as_u64())?;
+
+    ctx.say(format!("✅ Set {} as the role granted to approved members", role)).await?;
+
+    Ok(())
+}
+
+/// Set the channel in which members introduce themselves
+#[poise::command(slash_command)]
+async fn introductions_channel(
+    ctx: Context<'_>,
+    #[description = "Channel in which members can introduce themselves."] channel: Channel,
+) -> Result<(), Error> {
+    let bot = ctx.data();
+    let mut database = match utils::admin_database(ctx).await? {
+        Some(database) => database,
+        None => return Ok(()),
+    };
+    let guild_id = ctx.guild_id().unwrap();
+    let guild_key = format!("guild:{}", guild_id);
+
+    let channel = match GuildConfiguration::guild_channel(channel, "introductions channel") {
+        Ok(channel) => channel,
+        Err(error) => {
+            poise::send_reply(ctx, |reply| {
+                reply
+                    .content(format!("Got incorrect configuration: {}. Please make sure the value you pass is of the right type!", error))
+                    .ephemeral(true)
+            }).await?;
+
+            return Ok(());
+        },
+    };
+
+    let _: () = database.hset(&guild_key, "introductions_channel", channel.id.as_u64())?;
+    bot.set_introductions_channel(guild_id, channel.id).await;
+
+    ctx.say(format!("✅ Set {} as the channel in which members introduce themselves", channel)).await?;
+
+    Ok(())
+}
+
+/// Set the channel in which members pick roles
+#[poise::command(slash_command)]
+async fn role_assignment_channel(
+    ctx: Context<'_>,
+    #[description = "Channel in which members can assign themselves roles."] channel: Channel,
+) -> Result<(), Error> {
+    let mut database = match utils::admin_database(ctx).await? {
+        Some(database) => database,
+        None => return Ok(()),
+    };
+    let guild_id = ctx.guild_id().unwrap();
+    let guild_key = format!("guild:{}", guild_id);
+
+    let channel = match GuildConfiguration::guild_channel(channel, "role assignment channel") {
+        Ok(channel) => channel,
+        Err(error) => {
+            poise::send_reply(ctx, |reply| {
+                reply
+                    .content(format!("Got incorrect configuration: {}. Please make sure the value you pass is of the right type!", error))
+                    .ephemeral(true)
+            }).await?;
+
+            return Ok(());
+        },
+    };
+
+    let _: () = database.hset(&guild_key, "role_assignment_channel", channel.id.as_u64())?;
+
+    // Persistent messages posted in the previous channel are moved to the new one
+    messages::verify_persistent_messages(&ctx.serenity_context().http, &mut database, &guild_id).await?;
+
+    ctx.say(format!("✅ Set {} as the channel in which members pick roles", channel)).await?;
+
+    Ok(())
+}
+
+/// Set the category in which validation channels are created
+#[poise::command(slash_command)]
+async fn validation_category(
+    ctx: Context<'_>,
+    #[description = "Category in which to create private channels for member validation."] category: Channel,
+) -> Result<(), Error> {
+    let mut database = match utils::admin_database(ctx).await? {
+        Some(database) => database,
+        None => return Ok(()),
+    };
+    let guild_id = ctx.guild_id().unwrap();
+    let guild_key = format!("guild:{}", guild_id);
+
+    let category = match GuildConfiguration::category_channel(category, "validation channel") {
+        Ok(category) => category,
+        Err(error) => {
+            poise::send_reply(ctx, |reply| {
+                reply
+                    .content(format!("Got incorrect configuration: {}. Please make sure the value you pass is of the right type!", error))
+                    .ephemeral(true)
+            }).await?;
+
+            return Ok(());
+        },
+    };
+
+    let _: () = database.hset(&guild_key, "validation_category", category.id.as_u64())?;
+
+    ctx.say(format!("✅ Set {} as the category in which to create validation channels", category.id.mention())).await?;
+
+    Ok(())
+}
+
+/// Set the channel in which the welcome message is posted
+#[poise::command(slash_command)]
+async fn welcome_channel(
+    ctx: Context<'_>,
+    #[description = "Channel in which to post a welcome message for new members."] channel: Channel,
+) -> Result<(), Error> {
+    let mut database = match utils::admin_database(ctx).await? {
+        Some(database) => database,
+        None => return Ok(()),
+    };
+    let guild_id = ctx.guild_id().unwrap();
+    let guild_key = format!("guild:{}", guild_id);
+
+    let channel = match GuildConfiguration::guild_channel(channel, "welcome channel") {
+        Ok(channel) => channel,
+        Err(error) => {
+            poise::send_reply(ctx, |reply| {
+                reply
+                    .content(format!("Got incorrect configuration: {}. Please make sure the value you pass is of the right type!", error))
+                    .ephemeral(true)
+            }).await?;
+
+            return Ok(());
+        },
+    };
+
+    let _: () = database.hset(&guild_key, "welcome_channel", channel.id.as_u64())?;
+
+    // The welcome message is moved to the new channel, deleting it from the previous one. The
+    // channel is saved either way, so a failure is reported rather than failing the command.
+    let response = match messages::publish(&ctx.serenity_context().http, &mut database, &guild_id, &PersistentMessage::Welcome).await {
+        Ok(()) => format!("✅ Set {} as the channel in which the welcome message is posted", channel),
+        Err(error) => {
+            warn!("could not post the welcome message of guild {}: {}", guild_id, error);
+
+            format!(
+                "⚠️ Set {} as the channel in which the welcome message is posted, but I could not post it there. Check my permissions in this channel, then run /welcome post.",
+                channel,
+            )
+        },
+    };
+
+    ctx.say(response).await?;
+
+    Ok(())
+}
+
+/// Set the staff channel in which alerts and logs are posted
+#[poise::command(slash_command)]
+async fn log_channel(
+    ctx: Context<'_>,
+    #[description = "Staff channel in which to post alerts and logs, leave empty to stop logging."] channel: Option<Channel>,
+) -> Result<(), Error> {
+    let mut database = match utils::admin_database(ctx).await? {
+        Some(database) => database,
+        None => return Ok(()),
+    };
+    let guild_id = ctx.guild_id().unwrap();
+    let guild_key = format!("guild:{}", guild_id);
+
+    let channel = match channel.map(|channel| GuildConfiguration::guild_channel(channel, "log channel")).transpose() {
+        Ok(channel) => channel,
+        Err(error) => {
+            poise::send_reply(ctx, |reply| {
+                reply
+                    .content(format!("Got incorrect configuration: {}. Please make sure the value you pass is of the right type!", error))
+                    .ephemeral(true)
+            }).await?;
+
+            return Ok(());
+        },
+    };
+
+    match channel {
+        Some(channel) => {
+            let _: () = database.hset(&guild_key, "log_channel", channel.id.as_u64())?;
+
+            ctx.say(format!("✅ Set {} as the channel in which alerts and logs are posted", channel)).await?;
+        },
+        None => {
+            let _: () = database.hdel(&guild_key, "log_channel")?;
+
+            ctx.say("✅ Alerts and logs will no longer be posted").await?;
+        },
+    }
+
+    Ok(())
+}
+
+/// Set the staff role notified of new applications
+#[poise::command(slash_command)]
+async fn notify_role(
+    ctx: Context<'_>,
+    #[description = "The staff role to notify when a new member requests access to the server."] role: Role,
+) -> Result<(), Error> {
+    let mut database = match utils::admin_database(ctx).await? {
+        Some(database) => database,
+        None => return Ok(()),
+    };
+    let guild_id = ctx.guild_id().unwrap();
+
+    if let Some(problem) = utils::role_problem("notify role", &role, utils::bot_position(ctx).await?, false) {
+        poise::send_reply(ctx, |reply| {
+            reply
+                .content(problem)
+                .ephemeral(true)
+        }).await?;
+
+        return Ok(());
+    }
+
+    let _: () = database.hset(format!("onboarding:{}", guild_id), "notify_role", role.id.as_u64())?;
+
+    ctx.say(format!("✅ Set {} as the staff role to notify when new members join", role)).await?;
+
+    Ok(())
+}
+
+/// Set where validation discussions take place
+#[poise::command(slash_command)]
+async fn mode(
+    ctx: Context<'_>,
+    #[description = "Whether to hold validation discussions in private channels or private threads."] mode: OnboardingMode,
+) -> Result<(), Error> {
+    let mut database = match utils::admin_database(ctx).await? {
+        Some(database) => database,
+        None => return Ok(()),
+    };
+    let guild_id = ctx.guild_id().unwrap();
+
+    let onboarding_key = format!("onboarding:{}", guild_id);
+
+    if mode == OnboardingMode::Thread && !database.hexists(&onboarding_key, "threads_channel")? {
+        poise::send_reply(ctx, |reply| {
+            reply
+                .content("Private thread mode requires a channel in which to create the threads, please set one using /config set threads_channel first.")
+                .ephemeral(true)
+        }).await?;
+
+        return Ok(());
+    }
+
+    let _: () = database.hset(&onboarding_key, "mode", mode.name())?;
+
+    ctx.say(format!("✅ New members will now be validated in private {}s", mode)).await?;
+
+    Ok(())
+}
+
+/// Set the channel in which validation threads are created
+#[poise::command(slash_command)]
+async fn threads_channel(
+    ctx: Context<'_>,
+    #[description = "Staff-visible channel in which to create private validation threads."] channel: Channel,
+) -> Result<(), Error> {
+    let mut database = match utils::admin_database(ctx).await? {
+        Some(database) => database,
+        None => return Ok(()),
+    };
+    let guild_id = ctx.guild_id().unwrap();
+
+    let channel = match channel {
+        Channel::Guild(c) if c.kind == ChannelType::Text => c,
+        _ => {
+            poise::send_reply(ctx, |reply| {
+                reply
+                    .content("The channel in which to create validation threads must be a text channel.")
+                    .ephemeral(true)
+            }).await?;
+
+            return Ok(());
+        },
+    };
+
+    let _: () = database.hset(format!("onboarding:{}", guild_id), "threads_channel", channel.id.as_u64())?;
+
+    ctx.say(format!("✅ Set {} as the channel in which to create validation threads", channel)).await?;
+
+    Ok(())
+}
+
+/// Set how validation channels are named
+#[poise::command(slash_command)]
+async fn channel_name(
+    ctx: Context<'_>,
+    #[description = "Name of validation channels, e.g. verify-{n} with a counter, {username} or {id}."] template: String,
+) -> Result<(), Error> {
+    let mut database = match utils::admin_database(ctx).await? {
+        Some(database) => database,
+        None => return Ok(()),
+    };
+    let guild_id = ctx.guild_id().unwrap();
+
+    if template.is_empty() || template.len() > 100 {
+        poise::send_reply(ctx, |reply| {
+            reply
+                .content("The validation channel name template must be between 1 and 100 characters long.")
+                .ephemeral(true)
+        }).await?;
+
+        return Ok(());
+    }
+
+    let _: () = database.hset(format!("onboarding:{}", guild_id), "channel_name_template", &template)?;
+
+    let example = utils::validation_channel_name(&template, 1, ctx.author());
+
+    ctx.say(format!("✅ Validation channels will now be named like `{}`", example)).await?;
+
+    Ok(())
+}
+
+/// Show the current settings
+#[poise::command(slash_command)]
+async fn show(ctx: Context<'_>) -> Result<(), Error> {
+    let mut database = match utils::admin_database(ctx).await? {
+        Some(database) => database,
+        None => return Ok(()),
+    };
+    let guild_id = ctx.guild_id().unwrap();
+    let guild_key = format!("guild:{}", guild_id);
+    let onboarding_key = format!("onboarding:{}", guild_id);
+
+    let admin_role: u64 = database.hget(&guild_key, "admin_role")?;
+    let validated_role: u64 = database.hget(&guild_key, "validated_role")?;
+    let introductions_channel: u64 = database.hget(&guild_key, "introductions_channel")?;
+    let role_assignment_channel: u64 = database.hget(&guild_key, "role_assignment_channel")?;
+    let validation_category: u64 = database.hget(&guild_key, "validation_category")?;
+    let welcome_channel: u64 = database.hget(&guild_key, "welcome_channel")?;
+    let log_channel: Option<u64> = database.hget(&guild_key, "log_channel")?;
+    let notify_role: Option<u64> = database.hget(&onboarding_key, "notify_role")?;
+    let mode: Option<String> = database.hget(&onboarding_key, "mode")?;
+    let mode: OnboardingMode = mode.and_then(|mode| mode.parse().ok()).unwrap_or_default();
+    let threads_channel: Option<u64> = database.hget(&onboarding_key, "threads_channel")?;
+    let channel_name_template: Option<String> = database.hget(&onboarding_key, "channel_name_template")?;
+
+    let not_set = || "Not set".to_owned();
+
+    poise::send_reply(ctx, |reply| {
+        reply
+            .embed(|embed| {
+                embed
+                    .colour(Colour::BLURPLE)
+                    .title("Configuration")
+                    .field("Admin role", RoleId(admin_role).mention(), true)
+                    .field("Validated role", RoleId(validated_role).mention(), true)
+                    .field("Notify role", notify_role.map(|role| RoleId(role).mention().to_string()).unwrap_or_else(not_set), true)
+                    .field("Introductions channel", ChannelId(introductions_channel).mention(), true)
+                    .field("Role assignment channel", ChannelId(role_assignment_channel).mention(), true)
+                    .field("Welcome channel", ChannelId(welcome_channel).mention(), true)
+                    .field("Log channel", log_channel.map(|channel| ChannelId(channel).mention().to_string()).unwrap_or_else(not_set), true)
+                    .field("Validation category", ChannelId(validation_category).mention(), true)
+                    .field("Validation mode", mode, true)
+                    .field("Threads channel", threads_channel.map(|channel| ChannelId(channel).mention().to_string()).unwrap_or_else(not_set), true)
+                    .field("Validation channel names", format!("`{}`", channel_name_template.unwrap_or_else(|| utils::DEFAULT_CHANNEL_NAME_TEMPLATE.into())), true)
+            })
+            .ephemeral(true)
+    }).await?;
+
+    Ok(())
+}
+
+/// Check that everything configured still exists and that I have the permissions I need
+#[poise::command(slash_command)]
+async fn check(ctx: Context<'_>) -> Result<(), Error> {
+    let mut database = match utils::admin_database(ctx).await? {
+        Some(database) => database,
+        None => return Ok(()),
+    };
+
+    let guild = ctx.guild().unwrap();
+    let bot_member = guild.member(&ctx, ctx.serenity_context().cache.current_user_id()).await?;
+    let problems = utils::configuration_problems(&ctx.serenity_context().cache, &mut database, &guild, &bot_member)?;
+
+    poise::send_reply(ctx, |reply| {
+        reply
+            .embed(|embed| utils::configuration_problems_embed(&problems, embed))
+            .ephemeral(true)
+    }).await?;
+
+    Ok(())
+}
@@ -0,0 +1,198 @@
+use redis::Commands;
+
+use crate::{Context, Error};
+use crate::utils;
+use crate::models::{GuidePage, Toggle};
+
+/// Maximum length of a guide page title, as enforced by Discord for embed titles.
+const PAGE_TITLE_MAX_LENGTH: usize = 256;
+
+/// Maximum length of a guide page content, as enforced by Discord for embed descriptions.
+const PAGE_CONTENT_MAX_LENGTH: usize = 4096;
+
+/// Guide members through the server once they are approved
+#[poise::command(
+    slash_command,
+    subcommands("dm", "add", "remove", "reset", "show"),
+)]
+pub async fn guide(_: Context<'_>) -> Result<(), Error> {
+    Ok(())
+}
+
+/// Send the guide to members in private when they are approved
+#[poise::command(slash_command)]
+async fn dm(
+    ctx: Context<'_>,
+    #[description = "Whether to send the guide to approved members."] state: Toggle,
+) -> Result<(), Error> {
+    let mut database = match utils::admin_database(ctx).await? {
+        Some(database) => database,
+        None => return Ok(()),
+    };
+    let guild_id = ctx.guild_id().unwrap();
+
+    let onboarding_key = format!("onboarding:{}", guild_id);
+
+    match state {
+        Toggle::On => {
+            let _: () = database.hset(&onboarding_key, "guide_dm", true)?;
+
+            ctx.say("📖 Approved members will now receive the guide in private").await?;
+        },
+        Toggle::Off => {
+            let _: () = database.hdel(&onboarding_key, "guide_dm")?;
+
+            ctx.say("📖 Approved members will no longer receive the guide in private").await?;
+        },
+    }
+
+    Ok(())
+}
+
+/// Add a page to the guide, replacing the default pages
+#[poise::command(slash_command)]
+async fn add(
+    ctx: Context<'_>,
+    #[description = "The title of the page."] title: String,
+    #[description = "The content of the page, use \\n for line breaks."] content: String,
+    #[description = "The position of the page in the guide, at the end by default."] position: Option<usize>,
+) -> Result<(), Error> {
+    let mut database = match utils::admin_database(ctx).await? {
+        Some(database) => database,
+        None => return Ok(()),
+    };
+    let guild_id = ctx.guild_id().unwrap();
+
+    let content = content.replace("\\n", "\n");
+
+    if title.trim().is_empty() || title.chars().count() > PAGE_TITLE_MAX_LENGTH {
+        poise::send_reply(ctx, |reply| {
+            reply
+                .content(format!("Page titles must be between 1 and {} characters long.", PAGE_TITLE_MAX_LENGTH))
+                .ephemeral(true)
+        }).await?;
+
+        return Ok(());
+    }
+
+    if content.trim().is_empty() || content.chars().count() > PAGE_CONTENT_MAX_LENGTH {
+        poise::send_reply(ctx, |reply| {
+            reply
+                .content(format!("Page contents must be between 1 and {} characters long.", PAGE_CONTENT_MAX_LENGTH))
+                .ephemeral(true)
+        }).await?;
+
+        return Ok(());
+    }
+
+    // The first page added replaces the default guide
+    let custom_guide: bool = database.exists(format!("guide:{}", guild_id))?;
+    let mut pages = if custom_guide {
+        utils::guide_pages(&mut database, guild_id)?
+    } else {
+        Vec::new()
+    };
+
+    let position = position
+        .map(|position| position.saturating_sub(1).min(pages.len()))
+        .unwrap_or(pages.len());
+
+    pages.insert(position, GuidePage {
+        title,
+        content,
+    });
+
+    utils::save_guide_pages(&mut database, guild_id, &pages)?;
+
+    ctx.say(format!("📖 Added page {} of {} to the guide", position + 1, pages.len())).await?;
+
+    Ok(())
+}
+
+/// Remove a page from the guide
+#[poise::command(slash_command)]
+async fn remove(
+    ctx: Context<'_>,
+    #[description = "The number of the page to remove."] page: usize,
+) -> Result<(), Error> {
+    let mut database = match utils::admin_database(ctx).await? {
+        Some(database) => database,
+        None => return Ok(()),
+    };
+    let guild_id = ctx.guild_id().unwrap();
+
+    let custom_guide: bool = database.exists(format!("guide:{}", guild_id))?;
+    let mut pages = utils::guide_pages(&mut database, guild_id)?;
+
+    if !custom_guide || page == 0 || page > pages.len() {
+        poise::send_reply(ctx, |reply| {
+            reply
+                .content(format!("There is no page {} in the guide, only pages added with /guide add can be removed.", page))
+                .ephemeral(true)
+        }).await?;
+
+        return Ok(());
+    }
+
+    let removed_page = pages.remove(page - 1);
+
+    utils::save_guide_pages(&mut database, guild_id, &pages)?;
+
+    if pages.is_empty() {
+        ctx.say(format!("🗑️ Removed page **{}**, the guide is back to its default pages", removed_page.title)).await?;
+    } else {
+        ctx.say(format!("🗑️ Removed page **{}** from the guide", removed_page.title)).await?;
+    }
+
+    Ok(())
+}
+
+/// Go back to the default guide pages
+#[poise::command(slash_command)]
+async fn reset(ctx: Context<'_>) -> Result<(), Error> {
+    let mut database = match utils::admin_database(ctx).await? {
+        Some(database) => database,
+        None => return Ok(()),
+    };
+    let guild_id = ctx.guild_id().unwrap();
+
+    let _: () = database.del(format!("guide:{}", guild_id))?;
+
+    ctx.say("📖 The guide is back to its default pages").await?;
+
+    Ok(())
+}
+
+/// Read the server guide
+#[poise::command(slash_command)]
+async fn show(ctx: Context<'_>) -> Result<(), Error> {
+    let bot = ctx.data();
+    let guild_id = ctx.guild_id().unwrap();
+    let serves_guild = bot.serves_guild(guild_id).await?;
+
+    if !serves_guild {
+        poise::send_reply(ctx, |reply| {
+            reply
+                .content("I am not configured to work on this server! Have an admin configure me using the /setup command.")
+                .ephemeral(true)
+        }).await?;
+
+        return Ok(());
+    }
+
+    let pages = {
+        let mut database = bot.database.lock().await;
+
+        utils::guide_pages(&mut database, guild_id)?
+    };
+    let guild_name = ctx.guild().unwrap().name;
+
+    poise::send_reply(ctx, |reply| {
+        reply
+            .embed(|embed| utils::guide_embed(&guild_name, &pages, 0, embed))
+            .components(|components| utils::guide_components(guild_id, 0, pages.len(), components))
+            .ephemeral(true)
+    }).await?;
+
+    Ok(())
+}
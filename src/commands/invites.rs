@@ -0,0 +1,152 @@
+use std::collections::{BTreeSet, HashMap};
+
+use redis::Commands;
+use serenity::model::channel::{Channel, ChannelType};
+use serenity::model::id::{ChannelId, UserId};
+use serenity::prelude::Mentionable;
+use serenity::utils::Colour;
+
+use crate::{Context, Error};
+use crate::utils;
+use crate::models::InviteConversion;
+
+/// Track which invites new members join with
+#[poise::command(
+    slash_command,
+    subcommands("create", "report"),
+)]
+pub async fn invites(_: Context<'_>) -> Result<(), Error> {
+    Ok(())
+}
+
+/// Create a labelled invite, to find out how applicants found the server
+#[poise::command(slash_command)]
+async fn create(
+    ctx: Context<'_>,
+    #[description = "The label of the invite, e.g. where it is shared."] label: String,
+    #[description = "The channel the invite leads to, the welcome channel by default."] channel: Option<Channel>,
+) -> Result<(), Error> {
+    let mut database = match utils::admin_database(ctx).await? {
+        Some(database) => database,
+        None => return Ok(()),
+    };
+    let guild_id = ctx.guild_id().unwrap();
+    let guild_key = format!("guild:{}", guild_id);
+
+    let channel = match channel {
+        Some(Channel::Guild(c)) if c.kind == ChannelType::Text => c.id,
+        Some(_) => {
+            poise::send_reply(ctx, |reply| {
+                reply
+                    .content("Invites must lead to a text channel.")
+                    .ephemeral(true)
+            }).await?;
+
+            return Ok(());
+        },
+        None => ChannelId(database.hget(&guild_key, "welcome_channel")?),
+    };
+
+    let invite = channel.create_invite(&ctx, |invite| invite.max_age(0).unique(true)).await?;
+
+    // Cache the invite right away rather than relying on the invite creation event, which may
+    // arrive after someone already joined with it
+    let _: () = database.hset(format!("invites:{}", guild_id), &invite.code, 0)?;
+    let _: () = database.hset(format!("invite_inviters:{}", guild_id), &invite.code, ctx.author().id.as_u64())?;
+    let _: () = database.hset(format!("invite_labels:{}", guild_id), &invite.code, &label)?;
+
+    poise::send_reply(ctx, |reply| {
+        reply
+            .content(format!("🔗 Created invite **{}**: {}", label, invite.url()))
+            .ephemeral(true)
+    }).await?;
+
+    Ok(())
+}
+
+/// Show how many members joined, applied and were approved through each invite
+#[poise::command(slash_command)]
+async fn report(ctx: Context<'_>) -> Result<(), Error> {
+    let bot = ctx.data();
+    let guild_id = ctx.guild_id().unwrap();
+    let serves_guild = bot.serves_guild(guild_id).await?;
+
+    if !serves_guild {
+        poise::send_reply(ctx, |reply| {
+            reply
+                .content("I am not configured to work on this server! Have an admin configure me using the /setup command.")
+                .ephemeral(true)
+        }).await?;
+
+        return Ok(());
+    }
+
+    let mut database = bot.database.lock().await;
+
+    if !utils::is_staff(ctx, &mut database, guild_id).await? {
+        poise::send_reply(ctx, |reply| {
+            reply
+                .content("This is a staff command, you do not have the required rights to run it!")
+                .ephemeral(true)
+        }).await?;
+
+        return Ok(());
+    }
+
+    let labels: HashMap<String, String> = database.hgetall(format!("invite_labels:{}", guild_id))?;
+    let inviters: HashMap<String, u64> = database.hgetall(format!("invite_inviters:{}", guild_id))?;
+    let joins: HashMap<String, u64> = database.hgetall(format!("invite_joins:{}", guild_id))?;
+    let applications: HashMap<String, u64> = database.hgetall(InviteConversion::Application.key(guild_id))?;
+    let approvals: HashMap<String, u64> = database.hgetall(InviteConversion::Approval.key(guild_id))?;
+
+    let codes: BTreeSet<&String> = labels.keys().chain(joins.keys()).collect();
+
+    if codes.is_empty() {
+        poise::send_reply(ctx, |reply| {
+            reply
+                .content("No member joined through a tracked invite yet. Create labelled invites using the /invites create command.")
+                .ephemeral(true)
+        }).await?;
+
+        return Ok(());
+    }
+
+    let mut codes: Vec<&String> = codes.into_iter().collect();
+
+    codes.sort_by_key(|code| std::cmp::Reverse(joins.get(*code).copied().unwrap_or_default()));
+
+    poise::send_reply(ctx, |reply| {
+        reply
+            .embed(|embed| {
+                embed
+                    .colour(Colour::BLURPLE)
+                    .title("Invite conversions");
+
+                for code in codes.iter().take(utils::EMBED_MAX_FIELDS) {
+                    let joined = joins.get(*code).copied().unwrap_or_default();
+                    let applied = applications.get(*code).copied().unwrap_or_default();
+                    let approved = approvals.get(*code).copied().unwrap_or_default();
+                    let name = match labels.get(*code) {
+                        Some(label) => format!("{} (`{}`)", label, code),
+                        None => format!("`{}`", code),
+                    };
+                    let mut value = format!("{} joined, {} applied, {} approved", joined, applied, approved);
+
+                    if let Some(conversion) = (approved * 100).checked_div(joined) {
+                        value.push_str(&format!(" ({}% conversion)", conversion));
+                    }
+
+                    if let Some(inviter) = inviters.get(*code) {
+                        value.push_str(&format!("\nCreated by {}", UserId(*inviter).mention()));
+                    }
+
+                    embed.field(name, value, false);
+                }
+
+                embed
+            })
+            .ephemeral(true)
+    }).await?;
+
+    Ok(())
+}
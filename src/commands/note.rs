@@ -0,0 +1,185 @@
+use redis::Commands;
+use serenity::model::user::User;
+use serenity::model::Timestamp;
+use serenity::prelude::Mentionable;
+
+use crate::{Context, Error};
+use crate::models::Note;
+use crate::utils;
+
+/// Maximum length of a note, so that it fits in an embed field.
+const NOTE_MAX_LENGTH: usize = 1000;
+
+/// Keep notes about members across their applications
+#[poise::command(
+    slash_command,
+    subcommands("add", "list", "remove"),
+)]
+pub async fn note(_: Context<'_>) -> Result<(), Error> {
+    Ok(())
+}
+
+/// Write a note about a member
+#[poise::command(slash_command)]
+async fn add(
+    ctx: Context<'_>,
+    #[description = "The member the note is about."] user: User,
+    #[description = "The content of the note, up to 1000 characters."] content: String,
+) -> Result<(), Error> {
+    let bot = ctx.data();
+    let guild_id = ctx.guild_id().unwrap();
+    let serves_guild = bot.serves_guild(guild_id).await?;
+
+    if !serves_guild {
+        poise::send_reply(ctx, |reply| {
+            reply
+                .content("I am not configured to work on this server! Have an admin configure me using the /setup command.")
+                .ephemeral(true)
+        }).await?;
+
+        return Ok(());
+    }
+
+    let mut database = bot.database.lock().await;
+
+    if !utils::is_staff(ctx, &mut database, guild_id).await? {
+        poise::send_reply(ctx, |reply| {
+            reply
+                .content("This is a staff command, you do not have the required rights to run it!")
+                .ephemeral(true)
+        }).await?;
+
+        return Ok(());
+    }
+
+    if content.trim().is_empty() || content.chars().count() > NOTE_MAX_LENGTH {
+        poise::send_reply(ctx, |reply| {
+            reply
+                .content(format!("Notes must be between 1 and {} characters long.", NOTE_MAX_LENGTH))
+                .ephemeral(true)
+        }).await?;
+
+        return Ok(());
+    }
+
+    let note_id = database.incr(format!("note_counter:{}", guild_id), 1)?;
+    let note = Note {
+        id: note_id,
+        author: ctx.author().id,
+        timestamp: Timestamp::now().unix_timestamp(),
+        content,
+    };
+
+    let _: () = database.hset(format!("notes:{}:{}", guild_id, user.id), note_id, serde_json::to_string(&note)?)?;
+
+    poise::send_reply(ctx, |reply| {
+        reply
+            .content(format!("📝 Added note #{} about {}", note_id, user.mention()))
+            .ephemeral(true)
+    }).await?;
+
+    Ok(())
+}
+
+/// List the notes about a member
+#[poise::command(slash_command)]
+async fn list(
+    ctx: Context<'_>,
+    #[description = "The member whose notes to list."] user: User,
+) -> Result<(), Error> {
+    let bot = ctx.data();
+    let guild_id = ctx.guild_id().unwrap();
+    let serves_guild = bot.serves_guild(guild_id).await?;
+
+    if !serves_guild {
+        poise::send_reply(ctx, |reply| {
+            reply
+                .content("I am not configured to work on this server! Have an admin configure me using the /setup command.")
+                .ephemeral(true)
+        }).await?;
+
+        return Ok(());
+    }
+
+    let mut database = bot.database.lock().await;
+
+    if !utils::is_staff(ctx, &mut database, guild_id).await? {
+        poise::send_reply(ctx, |reply| {
+            reply
+                .content("This is a staff command, you do not have the required rights to run it!")
+                .ephemeral(true)
+        }).await?;
+
+        return Ok(());
+    }
+
+    let notes = utils::member_notes(&mut database, guild_id, user.id)?;
+
+    if notes.is_empty() {
+        poise::send_reply(ctx, |reply| {
+            reply
+                .content(format!("There are no notes about {}.", user.mention()))
+                .ephemeral(true)
+        }).await?;
+
+        return Ok(());
+    }
+
+    poise::send_reply(ctx, |reply| {
+        reply
+            .embed(|embed| utils::notes_embed(&user, &notes, embed))
+            .ephemeral(true)
+    }).await?;
+
+    Ok(())
+}
+
+/// Remove a note about a member
+#[poise::command(slash_command)]
+async fn remove(
+    ctx: Context<'_>,
+    #[description = "The member the note is about."] user: User,
+    #[description = "The number of the note to remove."] id: u64,
+) -> Result<(), Error> {
+    let bot = ctx.data();
+    let guild_id = ctx.guild_id().unwrap();
+    let serves_guild = bot.serves_guild(guild_id).await?;
+
+    if !serves_guild {
+        poise::send_reply(ctx, |reply| {
+            reply
+                .content("I am not configured to work on this server! Have an admin configure me using the /setup command.")
+                .ephemeral(true)
+        }).await?;
+
+        return Ok(());
+    }
+
+    let mut database = bot.database.lock().await;
+
+    if !utils::is_staff(ctx, &mut database, guild_id).await? {
+        poise::send_reply(ctx, |reply| {
+            reply
+                .content("This is a staff command, you do not have the required rights to run it!")
+                .ephemeral(true)
+        }).await?;
+
+        return Ok(());
+    }
+
+    let removed: u64 = database.hdel(format!("notes:{}:{}", guild_id, user.id), id)?;
+
+    let content = if removed > 0 {
+        format!("🗑️ Removed note #{} about {}", id, user.mention())
+    } else {
+        format!("There is no note #{} about {}.", id, user.mention())
+    };
+
+    poise::send_reply(ctx, |reply| {
+        reply
+            .content(content)
+            .ephemeral(true)
+    }).await?;
+
+    Ok(())
+}
@@ -1,17 +1,25 @@
 use redis::Commands;
 use serenity::model::application::component::ButtonStyle;
-use serenity::model::channel::PermissionOverwriteType;
-use serenity::model::guild::Role;
+use serenity::model::channel::{Channel, ChannelType, PermissionOverwriteType};
+use serenity::model::guild::{Member, Role};
 use serenity::model::id::{ChannelId, RoleId, UserId};
+use serenity::model::user::User;
+use serenity::model::Timestamp;
 use serenity::prelude::Mentionable;
+use serenity::utils::Colour;
+use tracing::debug;
 
 use crate::{Context, Error};
-use crate::identifiers;
+use crate::{events, identifiers, messages, tasks, utils};
+use crate::models::{HistoryEntry, HistoryEvent, InviteConversion, OnboardingMode, OnboardingPause, PersistentMessage, Toggle};
+
+/// Maximum number of members listed per category in an inactivity report, to fit in an embed field.
+const INACTIVITY_REPORT_MAX_MEMBERS: usize = 20;
 
 /// Configure onboarding in this guild
 #[poise::command(
     slash_command,
-    subcommands("configure", "approve", "deny"),
+    subcommands("configure", "permissions", "rules", "capacity", "raid_protection", "inactivity", "inactivity_report", "inactivity_enforcement", "risk_flags", "approval_roles", "trusted_role", "newcomer_role", "introductions", "stats", "lockdown", "pause", "resume", "history", "approve", "deny", "revoke"),
 )]
 pub async fn onboarding(_: Context<'_>) -> Result<(), Error>{
     Ok(())
@@ -22,6 +30,9 @@ pub async fn onboarding(_: Context<'_>) -> Result<(), Error>{
 async fn configure(
     ctx: Context<'_>,
     #[description = "The staff role to notify when a new member requests access to the server."] notify_role: Role,
+    #[description = "Whether to hold validation discussions in private channels or private threads."] mode: Option<OnboardingMode>,
+    #[description = "Staff-visible channel in which to create private validation threads."] threads_channel: Option<Channel>,
+    #[description = "Name of validation channels, e.g. verify-{n} with a counter, {username} or {id}."] channel_name: Option<String>,
 ) -> Result<(), Error> {
     let bot = ctx.data();
     let guild_id = ctx.guild_id().unwrap();
@@ -56,9 +67,883 @@ async fn configure(
 
     let onboarding_key = format!("onboarding:{}", guild_id);
 
+    let threads_channel = match threads_channel {
+        Some(Channel::Guild(c)) if c.kind == ChannelType::Text => Some(c),
+        Some(_) => {
+            poise::send_reply(ctx, |reply| {
+                reply
+                    .content("The channel in which to create validation threads must be a text channel.")
+                    .ephemeral(true)
+            }).await?;
+
+            return Ok(());
+        },
+        None => None,
+    };
+
+    if mode == Some(OnboardingMode::Thread) &&
+        threads_channel.is_none() &&
+        !database.hexists(&onboarding_key, "threads_channel")? {
+        poise::send_reply(ctx, |reply| {
+            reply
+                .content("Private thread mode requires a channel in which to create the threads, please pass one with the `threads_channel` parameter.")
+                .ephemeral(true)
+        }).await?;
+
+        return Ok(());
+    }
+
+    if let Some(template) = &channel_name {
+        if template.is_empty() || template.len() > 100 {
+            poise::send_reply(ctx, |reply| {
+                reply
+                    .content("The validation channel name template must be between 1 and 100 characters long.")
+                    .ephemeral(true)
+            }).await?;
+
+            return Ok(());
+        }
+    }
+
     database.hset(&onboarding_key, "notify_role", notify_role.id.as_u64())?;
 
-    ctx.say(format!("✅ Set {} as the staff role to notify when new members join", notify_role)).await?;
+    let mut response = format!("✅ Set {} as the staff role to notify when new members join", notify_role);
+
+    if let Some(threads_channel) = threads_channel {
+        let _: () = database.hset(&onboarding_key, "threads_channel", threads_channel.id.as_u64())?;
+
+        response.push_str(&format!("\n✅ Set {} as the channel in which to create validation threads", threads_channel));
+    }
+
+    if let Some(mode) = mode {
+        let _: () = database.hset(&onboarding_key, "mode", mode.name())?;
+
+        response.push_str(&format!("\n✅ New members will now be validated in private {}s", mode));
+    }
+
+    if let Some(channel_name) = channel_name {
+        let _: () = database.hset(&onboarding_key, "channel_name_template", &channel_name)?;
+
+        let example = utils::validation_channel_name(&channel_name, 1, ctx.author());
+
+        response.push_str(&format!("\n✅ Validation channels will now be named like `{}`", example));
+    }
+
+    ctx.say(response).await?;
+
+    Ok(())
+}
+
+/// Configure what applicants are allowed to do in their validation channel
+#[poise::command(slash_command)]
+async fn permissions(
+    ctx: Context<'_>,
+    #[description = "Allow applicants to upload files in their validation channel."] attach_files: bool,
+    #[description = "Allow links posted by applicants in their validation channel to be embedded."] embed_links: bool,
+) -> Result<(), Error> {
+    let mut database = match utils::admin_database(ctx).await? {
+        Some(database) => database,
+        None => return Ok(()),
+    };
+    let guild_id = ctx.guild_id().unwrap();
+
+    let onboarding_key = format!("onboarding:{}", guild_id);
+
+    let _: () = database.hset(&onboarding_key, "applicant_attach_files", attach_files)?;
+    let _: () = database.hset(&onboarding_key, "applicant_embed_links", embed_links)?;
+
+    let allowed = |allowed: bool| if allowed { "allowed" } else { "not allowed" };
+
+    ctx.say(format!(
+        "✅ Applicants are now {} to upload files and {} to embed links in new validation channels",
+        allowed(attach_files),
+        allowed(embed_links),
+    )).await?;
+
+    Ok(())
+}
+
+/// Require new members to accept the rules before applying
+#[poise::command(slash_command)]
+async fn rules(
+    ctx: Context<'_>,
+    #[description = "Whether new members must confirm they are 18+ and accept the rules before applying."] required: bool,
+    #[description = "The rules to display, use \\n for line breaks."] text: Option<String>,
+    #[description = "Version of the rules, members are asked to accept them again when it changes."] version: Option<String>,
+    #[description = "Channel in which to keep the rules posted and up to date."] channel: Option<Channel>,
+    #[description = "Stop keeping the rules posted in a channel."] unpost: Option<bool>,
+) -> Result<(), Error> {
+    let mut database = match utils::admin_database(ctx).await? {
+        Some(database) => database,
+        None => return Ok(()),
+    };
+    let guild_id = ctx.guild_id().unwrap();
+
+    let onboarding_key = format!("onboarding:{}", guild_id);
+
+    if required && text.is_none() && !database.hexists(&onboarding_key, "rules")? {
+        poise::send_reply(ctx, |reply| {
+            reply
+                .content("There are no rules to accept yet, please pass them with the `text` parameter.")
+                .ephemeral(true)
+        }).await?;
+
+        return Ok(());
+    }
+
+    if let Some(text) = text {
+        let _: () = database.hset(&onboarding_key, "rules", text.replace("\\n", "\n"))?;
+    }
+
+    if let Some(version) = version {
+        let _: () = database.hset(&onboarding_key, "rules_version", version)?;
+    }
+
+    let _: () = database.hset(&onboarding_key, "rules_required", required)?;
+
+    match channel {
+        Some(Channel::Guild(c)) if c.kind == ChannelType::Text => database.hset(&onboarding_key, "rules_channel", c.id.as_u64())?,
+        Some(_) => {
+            poise::send_reply(ctx, |reply| {
+                reply
+                    .content("The rules can only be posted in a text channel.")
+                    .ephemeral(true)
+            }).await?;
+
+            return Ok(());
+        },
+        None if unpost.unwrap_or_default() => database.hdel(&onboarding_key, "rules_channel")?,
+        None => (),
+    }
+
+    messages::publish(&ctx.serenity_context().http, &mut database, &guild_id, &PersistentMessage::Rules).await?;
+
+    if required {
+        let rules_version: Option<String> = database.hget(&onboarding_key, "rules_version")?;
+
+        ctx.say(format!(
+            "✅ New members must now confirm they are 18+ and accept the rules (version {}) before applying",
+            rules_version.unwrap_or_else(|| "1".into()),
+        )).await?;
+    } else {
+        ctx.say("✅ New members no longer need to accept the rules before applying").await?;
+    }
+
+    Ok(())
+}
+
+/// Limit how many applications can be open at the same time
+#[poise::command(slash_command)]
+async fn capacity(
+    ctx: Context<'_>,
+    #[description = "Maximum number of open validation channels, further applicants are placed in a waitlist. 0 for no limit."] max_open: u64,
+) -> Result<(), Error> {
+    let mut database = match utils::admin_database(ctx).await? {
+        Some(database) => database,
+        None => return Ok(()),
+    };
+    let guild_id = ctx.guild_id().unwrap();
+
+    let onboarding_key = format!("onboarding:{}", guild_id);
+
+    let _: () = database.hset(&onboarding_key, "max_open_applications", max_open)?;
+
+    let waiting: u64 = database.llen(format!("waitlist:{}", guild_id))?;
+
+    if max_open > 0 {
+        ctx.say(format!("✅ At most {} applications can now be open at the same time, {} member(s) are currently waiting", max_open, waiting)).await?;
+    } else {
+        ctx.say("✅ There is no limit on open applications anymore").await?;
+    }
+
+    // Raising or lifting the limit may free up slots for members in the waitlist
+    events::process_waitlist(ctx.serenity_context(), &mut database, &guild_id).await?;
+
+    Ok(())
+}
+
+/// Configure the thresholds above which onboarding is automatically locked down
+#[poise::command(slash_command)]
+async fn raid_protection(
+    ctx: Context<'_>,
+    #[description = "Maximum number of members joining within the time window, 0 to disable."] max_joins: u64,
+    #[description = "Maximum number of applications within the time window, 0 to disable."] max_applications: u64,
+    #[description = "Time window over which joins and applications are counted, in seconds."] window: u32,
+) -> Result<(), Error> {
+    let mut database = match utils::admin_database(ctx).await? {
+        Some(database) => database,
+        None => return Ok(()),
+    };
+    let guild_id = ctx.guild_id().unwrap();
+
+    if window == 0 {
+        poise::send_reply(ctx, |reply| {
+            reply
+                .content("The time window must be at least one second long.")
+                .ephemeral(true)
+        }).await?;
+
+        return Ok(());
+    }
+
+    let onboarding_key = format!("onboarding:{}", guild_id);
+
+    let _: () = database.hset(&onboarding_key, "raid_max_joins", max_joins)?;
+    let _: () = database.hset(&onboarding_key, "raid_max_applications", max_applications)?;
+    let _: () = database.hset(&onboarding_key, "raid_window", window)?;
+
+    ctx.say(format!(
+        "✅ Onboarding will be locked down when more than {} members join or more than {} applications are made within {} seconds",
+        max_joins,
+        max_applications,
+        window,
+    )).await?;
+
+    Ok(())
+}
+
+/// Remind, then kick, members who never start onboarding
+#[poise::command(slash_command)]
+async fn inactivity(
+    ctx: Context<'_>,
+    #[description = "Days after joining to remind members who haven't applied, 0 or empty to disable."] remind_after: Option<u64>,
+    #[description = "Days after joining to kick members who haven't applied, 0 or empty to disable."] kick_after: Option<u64>,
+) -> Result<(), Error> {
+    let mut database = match utils::admin_database(ctx).await? {
+        Some(database) => database,
+        None => return Ok(()),
+    };
+    let guild_id = ctx.guild_id().unwrap();
+
+    let onboarding_key = format!("onboarding:{}", guild_id);
+    let remind_after = remind_after.filter(|days| *days > 0);
+    let kick_after = kick_after.filter(|days| *days > 0);
+
+    if let (Some(remind_after), Some(kick_after)) = (remind_after, kick_after) {
+        if remind_after >= kick_after {
+            poise::send_reply(ctx, |reply| {
+                reply
+                    .content("Members must be reminded before they are kicked.")
+                    .ephemeral(true)
+            }).await?;
+
+            return Ok(());
+        }
+    }
+
+    match remind_after {
+        Some(remind_after) => database.hset(&onboarding_key, "inactivity_remind_days", remind_after)?,
+        None => database.hdel(&onboarding_key, "inactivity_remind_days")?,
+    }
+
+    match kick_after {
+        Some(kick_after) => database.hset(&onboarding_key, "inactivity_kick_days", kick_after)?,
+        None => database.hdel(&onboarding_key, "inactivity_kick_days")?,
+    }
+
+    let content = match (remind_after, kick_after) {
+        (None, None) => {
+            let _: () = database.srem(tasks::INACTIVITY_POLICIES_KEY, guild_id.as_u64())?;
+
+            "✅ Members who never start onboarding will no longer be reminded nor kicked".to_owned()
+        },
+        (remind_after, kick_after) => {
+            let mut steps = Vec::new();
+
+            if let Some(remind_after) = remind_after {
+                steps.push(format!("reminded after {} days", remind_after));
+            }
+
+            if let Some(kick_after) = kick_after {
+                steps.push(format!("kicked after {} days", kick_after));
+            }
+
+            if database.sismember(tasks::INACTIVITY_POLICIES_KEY, guild_id.as_u64())? {
+                format!(
+                    "✅ Members who never start onboarding will now be {}, starting with the next hourly check.",
+                    steps.join(" and "),
+                )
+            } else {
+                format!(
+                    "✅ Saved the inactivity policy: members who never start onboarding will be {}. Use /onboarding inactivity_report to see who would be affected, then /onboarding inactivity_enforcement to enable it.",
+                    steps.join(" and "),
+                )
+            }
+        },
+    };
+
+    ctx.say(content).await?;
+
+    Ok(())
+}
+
+/// List the members who would be reminded or kicked for never starting onboarding
+#[poise::command(slash_command)]
+async fn inactivity_report(ctx: Context<'_>) -> Result<(), Error> {
+    let bot = ctx.data();
+    let mut database = match utils::admin_database(ctx).await? {
+        Some(database) => database,
+        None => return Ok(()),
+    };
+    let guild_id = ctx.guild_id().unwrap();
+
+    let onboarding_key = format!("onboarding:{}", guild_id);
+
+    if !database.hexists(&onboarding_key, "inactivity_remind_days")? && !database.hexists(&onboarding_key, "inactivity_kick_days")? {
+        poise::send_reply(ctx, |reply| {
+            reply
+                .content("No inactivity policy is configured on this server! Configure one using the /onboarding inactivity command.")
+                .ephemeral(true)
+        }).await?;
+
+        return Ok(());
+    }
+
+    let enforced = database.sismember(tasks::INACTIVITY_POLICIES_KEY, guild_id.as_u64())?;
+
+    // Fetching every member takes a while, don't keep the rest of the bot waiting meanwhile
+    drop(database);
+
+    ctx.defer_ephemeral().await?;
+
+    let inactive_members = tasks::inactive_members(ctx.serenity_context(), &bot.database, &guild_id).await?;
+    let description = if enforced {
+        "Members who would be affected by the next inactivity check, run every hour"
+    } else {
+        "Members who would be affected if the inactivity policy was enabled, using /onboarding inactivity_enforcement"
+    };
+    let list = |members: &[Member]| {
+        if members.is_empty() {
+            return "Nobody".to_owned();
+        }
+
+        let mut list = members.iter()
+            .take(INACTIVITY_REPORT_MAX_MEMBERS)
+            .map(|member| match member.joined_at {
+                Some(joined_at) => format!("{}, joined <t:{}:R>", member.mention(), joined_at.unix_timestamp()),
+                None => member.mention().to_string(),
+            })
+            .collect::<Vec<String>>()
+            .join("\n");
+
+        if members.len() > INACTIVITY_REPORT_MAX_MEMBERS {
+            list.push_str(&format!("\n… and {} more", members.len() - INACTIVITY_REPORT_MAX_MEMBERS));
+        }
+
+        list
+    };
+
+    poise::send_reply(ctx, |reply| {
+        reply
+            .embed(|embed| {
+                embed
+                    .colour(Colour::ORANGE)
+                    .title("Inactivity report")
+                    .description(description)
+                    .field(format!("To remind ({})", inactive_members.to_remind.len()), list(&inactive_members.to_remind), false)
+                    .field(format!("To kick ({})", inactive_members.to_kick.len()), list(&inactive_members.to_kick), false)
+            })
+            .ephemeral(true)
+    }).await?;
+
+    Ok(())
+}
+
+/// Enable or disable reminding and kicking members who never start onboarding
+#[poise::command(slash_command)]
+async fn inactivity_enforcement(
+    ctx: Context<'_>,
+    #[description = "Whether to apply the inactivity policy every hour."] state: Toggle,
+) -> Result<(), Error> {
+    let mut database = match utils::admin_database(ctx).await? {
+        Some(database) => database,
+        None => return Ok(()),
+    };
+    let guild_id = ctx.guild_id().unwrap();
+
+    let onboarding_key = format!("onboarding:{}", guild_id);
+
+    match state {
+        Toggle::On => {
+            if !database.hexists(&onboarding_key, "inactivity_remind_days")? && !database.hexists(&onboarding_key, "inactivity_kick_days")? {
+                poise::send_reply(ctx, |reply| {
+                    reply
+                        .content("No inactivity policy is configured on this server! Configure one using the /onboarding inactivity command.")
+                        .ephemeral(true)
+                }).await?;
+
+                return Ok(());
+            }
+
+            let _: () = database.sadd(tasks::INACTIVITY_POLICIES_KEY, guild_id.as_u64())?;
+
+            utils::log_to_staff(&ctx, &mut database, guild_id, format!("⏰ {} enabled the inactivity policy", ctx.author().mention())).await?;
+
+            ctx.say("⏰ The inactivity policy is now enabled, members who never start onboarding will be handled by the next hourly check").await?;
+        },
+        Toggle::Off => {
+            let _: () = database.srem(tasks::INACTIVITY_POLICIES_KEY, guild_id.as_u64())?;
+
+            utils::log_to_staff(&ctx, &mut database, guild_id, format!("⏰ {} disabled the inactivity policy", ctx.author().mention())).await?;
+
+            ctx.say("⏰ The inactivity policy is now disabled, members who never start onboarding will no longer be reminded nor kicked").await?;
+        },
+    }
+
+    Ok(())
+}
+
+/// Configure which applicant traits are flagged to staff
+#[poise::command(slash_command)]
+async fn risk_flags(
+    ctx: Context<'_>,
+    #[description = "Flag accounts younger than this number of days, 0 to disable."] min_account_age: u32,
+    #[description = "Flag accounts that still have the default avatar."] default_avatar: bool,
+) -> Result<(), Error> {
+    let mut database = match utils::admin_database(ctx).await? {
+        Some(database) => database,
+        None => return Ok(()),
+    };
+    let guild_id = ctx.guild_id().unwrap();
+
+    let onboarding_key = format!("onboarding:{}", guild_id);
+
+    let _: () = database.hset(&onboarding_key, "risk_min_account_age", min_account_age)?;
+    let _: () = database.hset(&onboarding_key, "risk_flag_default_avatar", default_avatar)?;
+
+    let mut flags = vec![];
+
+    if min_account_age > 0 {
+        flags.push(format!("accounts younger than {} days", min_account_age));
+    }
+
+    if default_avatar {
+        flags.push("accounts with the default avatar".to_owned());
+    }
+
+    // Members who were denied before are always flagged
+    let flags = if flags.is_empty() {
+        "members who were denied before".to_owned()
+    } else {
+        format!("{} and members who were denied before", flags.join(", "))
+    };
+
+    ctx.say(format!("✅ Applicant summaries will now flag {}", flags)).await?;
+
+    Ok(())
+}
+
+/// Offer a role to grant to members when approving them
+#[poise::command(slash_command)]
+async fn approval_roles(
+    ctx: Context<'_>,
+    #[description = "The role to offer or stop offering."] role: Role,
+    #[description = "Whether staff can grant this role when approving a member."] offered: bool,
+) -> Result<(), Error> {
+    let mut database = match utils::admin_database(ctx).await? {
+        Some(database) => database,
+        None => return Ok(()),
+    };
+    let guild_id = ctx.guild_id().unwrap();
+
+    let approval_roles_key = format!("approval_roles:{}", guild_id);
+
+    if !offered {
+        let _: () = database.srem(&approval_roles_key, role.id.as_u64())?;
+
+        ctx.say(format!("✅ {} will no longer be offered when approving members", role)).await?;
+
+        return Ok(());
+    }
+
+    // Select menus are limited to 25 options
+    let approval_roles_count: usize = database.scard(&approval_roles_key)?;
+
+    if approval_roles_count >= 25 {
+        poise::send_reply(ctx, |reply| {
+            reply
+                .content("At most 25 roles can be offered when approving members, please remove one first.")
+                .ephemeral(true)
+        }).await?;
+
+        return Ok(());
+    }
+
+    let _: () = database.sadd(&approval_roles_key, role.id.as_u64())?;
+
+    ctx.say(format!("✅ {} will now be offered when approving members", role)).await?;
+
+    Ok(())
+}
+
+/// Set the role whose members can vouch for applicants
+#[poise::command(slash_command)]
+async fn trusted_role(
+    ctx: Context<'_>,
+    #[description = "The role allowed to vouch for applicants, leave empty to disable vouching."] role: Option<Role>,
+) -> Result<(), Error> {
+    let mut database = match utils::admin_database(ctx).await? {
+        Some(database) => database,
+        None => return Ok(()),
+    };
+    let guild_id = ctx.guild_id().unwrap();
+
+    let onboarding_key = format!("onboarding:{}", guild_id);
+
+    match role {
+        Some(role) => {
+            if let Some(problem) = utils::role_problem("trusted role", &role, utils::bot_position(ctx).await?, false) {
+                poise::send_reply(ctx, |reply| {
+                    reply
+                        .content(problem)
+                        .ephemeral(true)
+                }).await?;
+
+                return Ok(());
+            }
+
+            let _: () = database.hset(&onboarding_key, "trusted_role", role.id.as_u64())?;
+
+            ctx.say(format!("✅ Members with the {} role can now vouch for applicants", role)).await?;
+        },
+        None => {
+            let _: () = database.hdel(&onboarding_key, "trusted_role")?;
+
+            ctx.say("✅ Vouching is now disabled").await?;
+        },
+    }
+
+    Ok(())
+}
+
+/// Configure the role given to members from the moment they join until they are approved
+#[poise::command(slash_command)]
+async fn newcomer_role(
+    ctx: Context<'_>,
+    #[description = "The role given to members until they are approved, leave empty to disable."] role: Option<Role>,
+) -> Result<(), Error> {
+    let mut database = match utils::admin_database(ctx).await? {
+        Some(database) => database,
+        None => return Ok(()),
+    };
+    let guild_id = ctx.guild_id().unwrap();
+
+    let onboarding_key = format!("onboarding:{}", guild_id);
+
+    match role {
+        Some(role) => {
+            if let Some(problem) = utils::role_problem("newcomer role", &role, utils::bot_position(ctx).await?, true) {
+                poise::send_reply(ctx, |reply| {
+                    reply
+                        .content(problem)
+                        .ephemeral(true)
+                }).await?;
+
+                return Ok(());
+            }
+
+            let _: () = database.hset(&onboarding_key, "newcomer_role", role.id.as_u64())?;
+
+            ctx.say(format!("✅ Members will now have the {} role from the moment they join until they are approved", role)).await?;
+        },
+        None => {
+            let _: () = database.hdel(&onboarding_key, "newcomer_role")?;
+
+            ctx.say("✅ Members will no longer be given a role when they join").await?;
+        },
+    }
+
+    Ok(())
+}
+
+/// Configure how the bot guides members through the introductions channel
+#[poise::command(slash_command)]
+async fn introductions(
+    ctx: Context<'_>,
+    #[description = "Whether to create a thread on each introduction, for members to reply in."] threads: Option<bool>,
+    #[description = "Days after approval to remind members who haven't introduced themselves, 0 to disable."] remind_after: Option<u64>,
+    #[description = "Whether to remove new introductions from members who already posted one."] one_post: Option<bool>,
+) -> Result<(), Error> {
+    let mut database = match utils::admin_database(ctx).await? {
+        Some(database) => database,
+        None => return Ok(()),
+    };
+    let guild_id = ctx.guild_id().unwrap();
+    let guild_key = format!("guild:{}", guild_id);
+
+    let onboarding_key = format!("onboarding:{}", guild_id);
+
+    if let Some(threads) = threads {
+        let _: () = database.hset(&onboarding_key, "introductions_threads", threads)?;
+    }
+
+    match remind_after {
+        Some(0) => database.hdel(&onboarding_key, "introductions_remind_days")?,
+        Some(remind_after) => database.hset(&onboarding_key, "introductions_remind_days", remind_after)?,
+        None => (),
+    }
+
+    if let Some(one_post) = one_post {
+        let _: () = database.hset(&onboarding_key, "introductions_one_post", one_post)?;
+    }
+
+    let threads: Option<bool> = database.hget(&onboarding_key, "introductions_threads")?;
+    let remind_after: Option<u64> = database.hget(&onboarding_key, "introductions_remind_days")?;
+    let one_post: Option<bool> = database.hget(&onboarding_key, "introductions_one_post")?;
+    let introductions_channel = ChannelId(database.hget(&guild_key, "introductions_channel")?);
+
+    let mut content = format!("✅ Introductions in {} are now configured:", introductions_channel.mention());
+
+    content.push_str(if threads.unwrap_or_default() {
+        "\n- a thread is created on each introduction"
+    } else {
+        "\n- no thread is created on introductions"
+    });
+
+    match remind_after {
+        Some(remind_after) => content.push_str(&format!("\n- approved members are reminded to introduce themselves after {} days", remind_after)),
+        None => content.push_str("\n- approved members are not reminded to introduce themselves"),
+    }
+
+    content.push_str(if one_post.unwrap_or_default() {
+        "\n- members can only post one introduction"
+    } else {
+        "\n- members can post several introductions"
+    });
+
+    ctx.say(content).await?;
+
+    Ok(())
+}
+
+/// Show how many members are waiting at each stage of onboarding
+#[poise::command(slash_command)]
+async fn stats(ctx: Context<'_>) -> Result<(), Error> {
+    let bot = ctx.data();
+    let guild_id = ctx.guild_id().unwrap();
+    let serves_guild = bot.serves_guild(guild_id).await?;
+
+    if !serves_guild {
+        poise::send_reply(ctx, |reply| {
+            reply
+                .content("I am not configured to work on this server! Have an admin configure me using the /setup command.")
+                .ephemeral(true)
+        }).await?;
+
+        return Ok(());
+    }
+
+    let mut database = bot.database.lock().await;
+
+    if !utils::is_staff(ctx, &mut database, guild_id).await? {
+        poise::send_reply(ctx, |reply| {
+            reply
+                .content("This is a staff command, you do not have the required rights to run it!")
+                .ephemeral(true)
+        }).await?;
+
+        return Ok(());
+    }
+
+    let newcomer_role: Option<u64> = database.hget(format!("onboarding:{}", guild_id), "newcomer_role")?;
+    let newcomers: Vec<u64> = database.smembers(format!("newcomers:{}", guild_id))?;
+    let waitlist: Vec<u64> = database.lrange(format!("waitlist:{}", guild_id), 0, -1)?;
+    let open_validations: Vec<u64> = database.smembers(format!("open_validations:{}", guild_id))?;
+    let not_applied = match newcomer_role {
+        Some(_) => newcomers.iter()
+            .filter(|user_id| !waitlist.contains(user_id) && !open_validations.contains(user_id))
+            .count()
+            .to_string(),
+        None => "Unknown, configure a newcomer role using /onboarding newcomer_role".to_owned(),
+    };
+
+    poise::send_reply(ctx, |reply| {
+        reply
+            .embed(|embed| {
+                embed
+                    .colour(Colour::BLURPLE)
+                    .title("Onboarding stages")
+                    .field("Joined, not applied yet", not_applied, false)
+                    .field("Waiting for an application slot", waitlist.len(), true)
+                    .field("Under review", open_validations.len(), true)
+            })
+            .ephemeral(true)
+    }).await?;
+
+    Ok(())
+}
+
+/// Manually pause or resume the creation of new applications
+#[poise::command(slash_command)]
+async fn lockdown(
+    ctx: Context<'_>,
+    #[description = "Whether to pause the creation of new applications."] state: Toggle,
+) -> Result<(), Error> {
+    let mut database = match utils::admin_database(ctx).await? {
+        Some(database) => database,
+        None => return Ok(()),
+    };
+    let guild_id = ctx.guild_id().unwrap();
+
+    let onboarding_key = format!("onboarding:{}", guild_id);
+
+    match state {
+        Toggle::On => {
+            let _: () = database.hset(&onboarding_key, "lockdown", true)?;
+
+            utils::log_to_staff(&ctx, &mut database, guild_id, format!("🔒 {} locked down onboarding", ctx.author().mention())).await?;
+
+            ctx.say("🔒 Onboarding is now locked down, no new applications will be accepted until it is lifted").await?;
+        },
+        Toggle::Off => {
+            let _: () = database.hdel(&onboarding_key, "lockdown")?;
+
+            utils::log_to_staff(&ctx, &mut database, guild_id, format!("🔓 {} lifted the onboarding lockdown", ctx.author().mention())).await?;
+
+            ctx.say("🔓 Onboarding lockdown lifted, new members can apply again").await?;
+
+            events::process_waitlist(ctx.serenity_context(), &mut database, &guild_id).await?;
+        },
+    }
+
+    Ok(())
+}
+
+/// Close applications, e.g. while staff are unavailable
+#[poise::command(slash_command)]
+async fn pause(
+    ctx: Context<'_>,
+    #[description = "When to reopen applications, as a duration (e.g. 3d12h) or a date (e.g. 2023-08-01T09:00:00Z)."] until: Option<String>,
+    #[description = "Message shown to members trying to apply while applications are closed."] message: Option<String>,
+) -> Result<(), Error> {
+    let mut database = match utils::admin_database(ctx).await? {
+        Some(database) => database,
+        None => return Ok(()),
+    };
+    let guild_id = ctx.guild_id().unwrap();
+
+    let now = Timestamp::now().unix_timestamp();
+    let until = match until {
+        Some(until) => {
+            let until = utils::parse_duration(&until)
+                .and_then(|duration| now.checked_add(duration))
+                .filter(|until| Timestamp::from_unix_timestamp(*until).is_ok())
+                .or_else(|| Timestamp::parse(&until).ok().map(|until| until.unix_timestamp()));
+
+            match until {
+                Some(until) if until > now => Some(until),
+                _ => {
+                    poise::send_reply(ctx, |reply| {
+                        reply
+                            .content("I couldn't understand when to reopen applications, please give a duration such as `3d12h` or a future date such as `2023-08-01T09:00:00Z`.")
+                            .ephemeral(true)
+                    }).await?;
+
+                    return Ok(());
+                },
+            }
+        },
+        None => None,
+    };
+
+    let pause = OnboardingPause { until, message };
+
+    events::pause_onboarding(&ctx.serenity_context().http, &mut database, &guild_id, &pause).await?;
+
+    utils::log_to_staff(&ctx, &mut database, guild_id, format!("🚧 {} closed applications", ctx.author().mention())).await?;
+
+    ctx.say(format!("🚧 {}", pause.notice())).await?;
+
+    Ok(())
+}
+
+/// Reopen applications
+#[poise::command(slash_command)]
+async fn resume(ctx: Context<'_>) -> Result<(), Error> {
+    let mut database = match utils::admin_database(ctx).await? {
+        Some(database) => database,
+        None => return Ok(()),
+    };
+    let guild_id = ctx.guild_id().unwrap();
+
+    events::resume_onboarding(ctx.serenity_context(), &mut database, &guild_id).await?;
+
+    utils::log_to_staff(&ctx, &mut database, guild_id, format!("🔓 {} reopened applications", ctx.author().mention())).await?;
+
+    ctx.say("🔓 Applications are open again").await?;
+
+    Ok(())
+}
+
+/// Show a member's onboarding history and the notes staff wrote about them
+#[poise::command(slash_command)]
+async fn history(
+    ctx: Context<'_>,
+    #[description = "The member whose history to show."] user: User,
+) -> Result<(), Error> {
+    let bot = ctx.data();
+    let guild_id = ctx.guild_id().unwrap();
+    let serves_guild = bot.serves_guild(guild_id).await?;
+
+    if !serves_guild {
+        poise::send_reply(ctx, |reply| {
+            reply
+                .content("I am not configured to work on this server! Have an admin configure me using the /setup command.")
+                .ephemeral(true)
+        }).await?;
+
+        return Ok(());
+    }
+
+    let mut database = bot.database.lock().await;
+
+    if !utils::is_staff(ctx, &mut database, guild_id).await? {
+        poise::send_reply(ctx, |reply| {
+            reply
+                .content("This is a staff command, you do not have the required rights to run it!")
+                .ephemeral(true)
+        }).await?;
+
+        return Ok(());
+    }
+
+    let history = utils::member_history(&mut database, guild_id, user.id)?;
+    let notes = utils::member_notes(&mut database, guild_id, user.id)?;
+
+    // Keep the most recent entries, so that the description fits in an embed
+    let history = history.iter()
+        .skip(history.len().saturating_sub(30))
+        .map(|entry| {
+            let mut line = format!("<t:{}:f> — {}", entry.timestamp, entry.event);
+
+            if let Some(actor) = entry.actor {
+                line.push_str(&format!(" by {}", actor.mention()));
+            }
+
+            if let Some(details) = &entry.details {
+                line.push_str(&format!(": {}", details));
+            }
+
+            line
+        })
+        .collect::<Vec<_>>();
+
+    poise::send_reply(ctx, |reply| {
+        reply
+            .embed(|embed| {
+                embed
+                    .colour(Colour::BLITZ_BLUE)
+                    .title(format!("Onboarding history of {}", utils::tag(&user.name, user.discriminator)))
+                    .description(if history.is_empty() { "No recorded events.".into() } else { history.join("\n") })
+            })
+            .ephemeral(true);
+
+        if !notes.is_empty() {
+            reply.embed(|embed| utils::notes_embed(&user, &notes, embed));
+        }
+
+        reply
+    }).await?;
 
     Ok(())
 }
@@ -114,8 +999,15 @@ async fn approve(ctx: Context<'_>) -> Result<(), Error> {
     let user_id: u64 = database.hget(validation_channel_to_user_key, channel_id.as_u64())?;
     let user_id = UserId(user_id);
 
-    // Remove approved member's access to the validation channel
-    channel_id.delete_permission(&ctx, PermissionOverwriteType::Member(user_id)).await?;
+    // Remove approved member's access to the validation channel or thread
+    match channel_id.to_channel(&ctx).await? {
+        Channel::Guild(channel) if channel.kind == ChannelType::PrivateThread => {
+            channel_id.remove_thread_member(&ctx, user_id).await?;
+        },
+        _ => {
+            channel_id.delete_permission(&ctx, PermissionOverwriteType::Member(user_id)).await?;
+        },
+    }
 
     let mut member = guild_id.member(&ctx, user_id).await?;
     let validated_role = database.hget(&guild_key, "validated_role")?;
@@ -123,10 +1015,49 @@ async fn approve(ctx: Context<'_>) -> Result<(), Error> {
 
     member.add_role(ctx, validated_role).await?;
 
+    let newcomer_role: Option<u64> = database.hget(format!("onboarding:{}", guild_id), "newcomer_role")?;
+
+    if let Some(newcomer_role) = newcomer_role.map(RoleId) {
+        if member.roles.contains(&newcomer_role) {
+            member.remove_role(ctx, newcomer_role).await?;
+        }
+    }
+
+    let _: () = database.srem(format!("newcomers:{}", guild_id), user_id.as_u64())?;
+
+    utils::record_history(&mut database, guild_id, user_id, &HistoryEntry::new(HistoryEvent::Approval, Some(ctx.author().id), None))?;
+    events::schedule_introduction_reminder(&mut database, &guild_id, &user_id)?;
+    utils::record_invite_conversion(&mut database, guild_id, user_id, InviteConversion::Approval)?;
+    events::release_application_slot(ctx.serenity_context(), &mut database, &guild_id, &user_id).await?;
+
+    let approval_roles: Vec<u64> = database.smembers(format!("approval_roles:{}", guild_id))?;
+    let approval_roles = approval_roles.into_iter()
+        .filter_map(|role| guild.roles.get(&RoleId(role)))
+        .collect::<Vec<_>>();
+
     poise::send_reply(ctx, |reply| {
         reply
-            .content(format!("Approved {} ({}#{})", member.user, member.user.name, member.user.discriminator))
+            .content(format!("Approved {} ({})", member.user, utils::member_tag(&member)))
             .components(|components| {
+                if !approval_roles.is_empty() {
+                    components.create_action_row(|row| {
+                        row.create_select_menu(|menu| {
+                            menu
+                                .custom_id(identifiers::ONBOARDING_APPROVAL_ROLES)
+                                .placeholder("Grant additional roles")
+                                .min_values(1)
+                                .max_values(approval_roles.len() as u64)
+                                .options(|options| {
+                                    for role in &approval_roles {
+                                        options.create_option(|option| option.label(&role.name).value(role.id));
+                                    }
+
+                                    options
+                                })
+                        })
+                    });
+                }
+
                 components.create_action_row(|row| {
                     row
                         .create_button(|button| {
@@ -145,22 +1076,48 @@ async fn approve(ctx: Context<'_>) -> Result<(), Error> {
             })
     }).await?;
 
-    if let Some(system_channel) = guild.system_channel_id {
-        let introductions_channel = database.hget(&guild_key, "introductions_channel")?;
-        let introductions_channel = ChannelId(introductions_channel);
-        let role_assignment_channel = database.hget(&guild_key, "role_assignment_channel")?;
-        let role_assignment_channel = ChannelId(role_assignment_channel);
+    let guide_dm: Option<bool> = database.hget(format!("onboarding:{}", guild_id), "guide_dm")?;
+    let mut guide_undelivered = false;
 
-        system_channel.send_message(&ctx, |message| {
-            message.content(format!(
-                "👋 Welcome {} to Transpouce! Feel free to grab some roles in {}, and to write a few words about yourself in {} if you like. Have a pleasant stay here! 🤗",
-                member.mention(),
-                role_assignment_channel.mention(),
-                introductions_channel.mention(),
-            ))
-        }).await?;
+    if guide_dm.unwrap_or_default() {
+        let pages = utils::guide_pages(&mut database, guild_id)?;
+
+        let sent = member.user.direct_message(&ctx, |message| {
+            message
+                .embed(|embed| utils::guide_embed(&guild.name, &pages, 0, embed))
+                .components(|components| utils::guide_components(guild_id, 0, pages.len(), components))
+        }).await;
+
+        // Members who closed their direct messages can still read the guide with /guide show
+        if let Err(error) = sent {
+            debug!("could not send the guide to member {} of guild {}: {}", member.user.id, guild_id, error);
+
+            guide_undelivered = true;
+        }
+    }
+
+    let welcome_channel = match guild.system_channel_id {
+        Some(system_channel) => system_channel,
+        None => ChannelId(database.hget(&guild_key, "welcome_channel")?),
+    };
+    let introductions_channel = database.hget(&guild_key, "introductions_channel")?;
+    let introductions_channel = ChannelId(introductions_channel);
+    let role_assignment_channel = database.hget(&guild_key, "role_assignment_channel")?;
+    let role_assignment_channel = ChannelId(role_assignment_channel);
+
+    let mut content = format!(
+        "👋 Welcome {} to Transpouce! Feel free to grab some roles in {}, and to write a few words about yourself in {} if you like. Have a pleasant stay here! 🤗",
+        member.mention(),
+        role_assignment_channel.mention(),
+        introductions_channel.mention(),
+    );
+
+    if guide_undelivered {
+        content.push_str("\n📖 I couldn't send you the server guide in private, run /guide show to read it here.");
     }
 
+    welcome_channel.send_message(&ctx, |message| message.content(content)).await?;
+
     Ok(())
 }
 
@@ -217,8 +1174,79 @@ async fn deny(ctx: Context<'_>) -> Result<(), Error> {
 
     member.kick_with_reason(&ctx, "Denied at validation").await?;
 
+    utils::record_history(&mut database, guild_id, user_id, &HistoryEntry::new(HistoryEvent::Denial, Some(ctx.author().id), None))?;
+    events::release_application_slot(ctx.serenity_context(), &mut database, &guild_id, &user_id).await?;
+
     poise::send_reply(ctx, |reply| {
-        reply.content(format!("Denied {} ({}#{})", member.user, member.user.name, member.user.discriminator))
+        reply.content(format!("Denied {} ({})", member.user, utils::member_tag(&member)))
+    }).await?;
+
+    Ok(())
+}
+
+/// Revoke a member's validation and reopen their onboarding.
+#[poise::command(slash_command)]
+async fn revoke(
+    ctx: Context<'_>,
+    #[description = "The member whose validation to revoke."] mut member: Member,
+    #[description = "Why the member's validation is revoked."] reason: Option<String>,
+) -> Result<(), Error> {
+    let mut database = match utils::admin_database(ctx).await? {
+        Some(database) => database,
+        None => return Ok(()),
+    };
+    let guild_id = ctx.guild_id().unwrap();
+    let guild_key = format!("guild:{}", guild_id);
+
+    let validated_role = database.hget(&guild_key, "validated_role")?;
+    let validated_role = RoleId(validated_role);
+
+    if member.roles.contains(&validated_role) {
+        member.remove_role(&ctx, validated_role).await?;
+    }
+
+    let newcomer_role: Option<u64> = database.hget(format!("onboarding:{}", guild_id), "newcomer_role")?;
+
+    if let Some(newcomer_role) = newcomer_role.map(RoleId) {
+        member.add_role(&ctx, newcomer_role).await?;
+        let _: () = database.sadd(format!("newcomers:{}", guild_id), member.user.id.as_u64())?;
+    }
+
+    utils::record_history(&mut database, guild_id, member.user.id, &HistoryEntry::new(HistoryEvent::Revocation, Some(ctx.author().id), reason.clone()))?;
+
+    let reason = reason.map(|reason| format!(": {}", reason)).unwrap_or_default();
+
+    match events::pending_validation(&mut database, &guild_id, &member.user.id).await? {
+        Some(validation_channel) => {
+            events::restore_member_verification(ctx.serenity_context(), &mut database, &member, validation_channel).await?;
+
+            validation_channel.say(&ctx, format!(
+                "🔁 {}, your validation was revoked by {}{}. A staff member will be with you soon.",
+                member.mention(),
+                ctx.author().mention(),
+                reason,
+            )).await?;
+        },
+        None => events::open_member_verification(ctx.serenity_context(), &mut database, &member).await?,
+    }
+
+    let validation_channel = events::pending_validation(&mut database, &guild_id, &member.user.id).await?;
+
+    utils::log_to_staff(&ctx, &mut database, guild_id, format!(
+        "🔁 {} revoked the validation of {}{}",
+        ctx.author().mention(),
+        member.mention(),
+        reason,
+    )).await?;
+
+    poise::send_reply(ctx, |reply| {
+        let mut content = format!("Revoked the validation of {} ({})", member.user, utils::member_tag(&member));
+
+        if let Some(validation_channel) = validation_channel {
+            content.push_str(&format!(", their onboarding continues in {}", validation_channel.mention()));
+        }
+
+        reply.content(content)
     }).await?;
 
     Ok(())
@@ -0,0 +1,137 @@
+use redis::Commands;
+use serenity::model::channel::{Channel, ChannelType};
+use serenity::prelude::Mentionable;
+use serenity::utils::Colour;
+
+use crate::{Context, Error};
+use crate::{messages, utils};
+use crate::models::{Panel, PersistentMessage};
+
+/// Keep informational messages posted and up to date
+#[poise::command(
+    slash_command,
+    subcommands("set", "remove", "list"),
+)]
+pub async fn panel(_: Context<'_>) -> Result<(), Error> {
+    Ok(())
+}
+
+/// Post an informational panel, or update an existing one
+#[poise::command(slash_command)]
+async fn set(
+    ctx: Context<'_>,
+    #[description = "Short name identifying the panel, e.g. faq."] name: String,
+    #[description = "The channel in which to post the panel."] channel: Channel,
+    #[description = "The title of the panel."] title: String,
+    #[description = "The content of the panel, use \\n for line breaks."] content: String,
+) -> Result<(), Error> {
+    let mut database = match utils::admin_database(ctx).await? {
+        Some(database) => database,
+        None => return Ok(()),
+    };
+    let guild_id = ctx.guild_id().unwrap();
+
+    let name = match utils::validated_name(ctx, "Panel", &name).await? {
+        Some(name) => name,
+        None => return Ok(()),
+    };
+
+    let channel = match channel {
+        Channel::Guild(c) if c.kind == ChannelType::Text => c,
+        _ => {
+            poise::send_reply(ctx, |reply| {
+                reply
+                    .content("Panels can only be posted in a text channel.")
+                    .ephemeral(true)
+            }).await?;
+
+            return Ok(());
+        },
+    };
+
+    let panel = Panel {
+        name,
+        channel: channel.id,
+        title,
+        content: content.replace("\\n", "\n"),
+    };
+
+    let _: () = database.hset(format!("panels:{}", guild_id), &panel.name, serde_json::to_string(&panel)?)?;
+    messages::publish(&ctx.serenity_context().http, &mut database, &guild_id, &PersistentMessage::Panel(panel.name.clone())).await?;
+
+    ctx.say(format!("📌 Panel `{}` is now posted in {}", panel.name, channel.mention())).await?;
+
+    Ok(())
+}
+
+/// Delete an informational panel
+#[poise::command(slash_command)]
+async fn remove(
+    ctx: Context<'_>,
+    #[description = "The name of the panel."] name: String,
+) -> Result<(), Error> {
+    let mut database = match utils::admin_database(ctx).await? {
+        Some(database) => database,
+        None => return Ok(()),
+    };
+    let guild_id = ctx.guild_id().unwrap();
+
+    let name = name.trim().to_lowercase();
+    let removed: u64 = database.hdel(format!("panels:{}", guild_id), &name)?;
+
+    if removed == 0 {
+        poise::send_reply(ctx, |reply| {
+            reply
+                .content(format!("There is no panel named `{}`.", name))
+                .ephemeral(true)
+        }).await?;
+
+        return Ok(());
+    }
+
+    messages::remove(&ctx.serenity_context().http, &mut database, &guild_id, &PersistentMessage::Panel(name.clone())).await?;
+
+    ctx.say(format!("🗑️ Deleted panel `{}`", name)).await?;
+
+    Ok(())
+}
+
+/// List the informational panels of this server
+#[poise::command(slash_command)]
+async fn list(ctx: Context<'_>) -> Result<(), Error> {
+    let mut database = match utils::admin_database(ctx).await? {
+        Some(database) => database,
+        None => return Ok(()),
+    };
+    let guild_id = ctx.guild_id().unwrap();
+
+    let panels = messages::panels(&mut database, &guild_id)?;
+
+    if panels.is_empty() {
+        poise::send_reply(ctx, |reply| {
+            reply
+                .content("There are no panels yet. Post one using /panel set.")
+                .ephemeral(true)
+        }).await?;
+
+        return Ok(());
+    }
+
+    poise::send_reply(ctx, |reply| {
+        reply
+            .embed(|embed| {
+                embed
+                    .colour(Colour::BLURPLE)
+                    .title("Panels");
+
+                for panel in panels.iter().take(utils::EMBED_MAX_FIELDS) {
+                    embed.field(format!("{} (`{}`)", panel.title, panel.name), format!("Posted in {}", panel.channel.mention()), false);
+                }
+
+                embed
+            })
+            .ephemeral(true)
+    }).await?;
+
+    Ok(())
+}
@@ -0,0 +1,244 @@
+use std::collections::HashMap;
+
+use redis::Commands;
+use serenity::model::guild::{Member, Role};
+use serenity::model::id::RoleId;
+use serenity::model::user::User;
+use serenity::prelude::Mentionable;
+
+use crate::{Context, Error};
+use crate::utils;
+use crate::models::PronounsPreset;
+
+/// Maximum length of the pronouns a member can set.
+const PRONOUNS_MAX_LENGTH: usize = 100;
+
+/// Share your pronouns with the server
+#[poise::command(
+    slash_command,
+    subcommands("set", "clear", "of", "role"),
+)]
+pub async fn pronouns(_: Context<'_>) -> Result<(), Error> {
+    Ok(())
+}
+
+/// Set your pronouns
+#[poise::command(slash_command)]
+async fn set(
+    ctx: Context<'_>,
+    #[description = "Common pronouns."] preset: Option<PronounsPreset>,
+    #[description = "Your pronouns in your own words, e.g. xe/xem or she/her, in English."] custom: Option<String>,
+) -> Result<(), Error> {
+    let bot = ctx.data();
+    let guild_id = ctx.guild_id().unwrap();
+    let serves_guild = bot.serves_guild(guild_id).await?;
+
+    if !serves_guild {
+        poise::send_reply(ctx, |reply| {
+            reply
+                .content("I am not configured to work on this server! Have an admin configure me using the /setup command.")
+                .ephemeral(true)
+        }).await?;
+
+        return Ok(());
+    }
+
+    let pronouns = match (preset, custom) {
+        (Some(preset), None) => preset.name().to_owned(),
+        (None, Some(custom)) if !custom.trim().is_empty() && custom.trim().chars().count() <= PRONOUNS_MAX_LENGTH => custom.trim().to_owned(),
+        (None, Some(_)) => {
+            poise::send_reply(ctx, |reply| {
+                reply
+                    .content(format!("Pronouns must be between 1 and {} characters long.", PRONOUNS_MAX_LENGTH))
+                    .ephemeral(true)
+            }).await?;
+
+            return Ok(());
+        },
+        _ => {
+            poise::send_reply(ctx, |reply| {
+                reply
+                    .content("Pick either a preset or write your pronouns in your own words.")
+                    .ephemeral(true)
+            }).await?;
+
+            return Ok(());
+        },
+    };
+
+    let mut member = guild_id.member(&ctx, ctx.author().id).await?;
+    let mut database = bot.database.lock().await;
+
+    let _: () = database.hset(format!("pronouns:{}", guild_id), ctx.author().id.as_u64(), &pronouns)?;
+
+    let pronoun_roles: HashMap<String, u64> = database.hgetall(format!("pronoun_roles:{}", guild_id))?;
+
+    sync_pronoun_roles(&ctx, &mut member, &pronoun_roles, Some(&pronouns)).await?;
+
+    poise::send_reply(ctx, |reply| {
+        reply
+            .content(format!("🏷️ Your pronouns are now **{}**", pronouns))
+            .ephemeral(true)
+    }).await?;
+
+    Ok(())
+}
+
+/// Remove your pronouns
+#[poise::command(slash_command)]
+async fn clear(ctx: Context<'_>) -> Result<(), Error> {
+    let bot = ctx.data();
+    let guild_id = ctx.guild_id().unwrap();
+    let serves_guild = bot.serves_guild(guild_id).await?;
+
+    if !serves_guild {
+        poise::send_reply(ctx, |reply| {
+            reply
+                .content("I am not configured to work on this server! Have an admin configure me using the /setup command.")
+                .ephemeral(true)
+        }).await?;
+
+        return Ok(());
+    }
+
+    let mut member = guild_id.member(&ctx, ctx.author().id).await?;
+    let mut database = bot.database.lock().await;
+
+    let _: () = database.hdel(format!("pronouns:{}", guild_id), ctx.author().id.as_u64())?;
+
+    let pronoun_roles: HashMap<String, u64> = database.hgetall(format!("pronoun_roles:{}", guild_id))?;
+
+    sync_pronoun_roles(&ctx, &mut member, &pronoun_roles, None).await?;
+
+    poise::send_reply(ctx, |reply| {
+        reply
+            .content("🏷️ Your pronouns were removed")
+            .ephemeral(true)
+    }).await?;
+
+    Ok(())
+}
+
+/// Show a member's pronouns
+#[poise::command(slash_command)]
+async fn of(
+    ctx: Context<'_>,
+    #[description = "The member whose pronouns to show."] user: User,
+) -> Result<(), Error> {
+    show(ctx, user).await
+}
+
+/// Show a member's pronouns
+#[poise::command(context_menu_command = "Show pronouns")]
+pub async fn show_pronouns(
+    ctx: Context<'_>,
+    #[description = "The member whose pronouns to show."] user: User,
+) -> Result<(), Error> {
+    show(ctx, user).await
+}
+
+/// Map pronouns to a role given to members who set them
+#[poise::command(slash_command)]
+async fn role(
+    ctx: Context<'_>,
+    #[description = "The pronouns, e.g. she/her."] pronouns: String,
+    #[description = "The role to give to members with these pronouns, leave empty to remove the mapping."] role: Option<Role>,
+) -> Result<(), Error> {
+    let mut database = match utils::admin_database(ctx).await? {
+        Some(database) => database,
+        None => return Ok(()),
+    };
+    let guild_id = ctx.guild_id().unwrap();
+
+    let pronoun_roles_key = format!("pronoun_roles:{}", guild_id);
+    let pronouns = normalize_pronouns(&pronouns);
+
+    match role {
+        Some(role) => {
+            let _: () = database.hset(&pronoun_roles_key, &pronouns, role.id.as_u64())?;
+
+            ctx.say(format!("✅ Members who set their pronouns to **{}** will now get the {} role", pronouns, role)).await?;
+        },
+        None => {
+            let _: () = database.hdel(&pronoun_roles_key, &pronouns)?;
+
+            ctx.say(format!("✅ Members who set their pronouns to **{}** will no longer get a role", pronouns)).await?;
+        },
+    }
+
+    Ok(())
+}
+
+async fn show(ctx: Context<'_>, user: User) -> Result<(), Error> {
+    let bot = ctx.data();
+    let guild_id = ctx.guild_id().unwrap();
+    let serves_guild = bot.serves_guild(guild_id).await?;
+
+    if !serves_guild {
+        poise::send_reply(ctx, |reply| {
+            reply
+                .content("I am not configured to work on this server! Have an admin configure me using the /setup command.")
+                .ephemeral(true)
+        }).await?;
+
+        return Ok(());
+    }
+
+    let pronouns: Option<String> = {
+        let mut database = bot.database.lock().await;
+
+        database.hget(format!("pronouns:{}", guild_id), user.id.as_u64())?
+    };
+
+    let content = match pronouns {
+        Some(pronouns) => format!("🏷️ {}'s pronouns are **{}**", user.mention(), pronouns),
+        None => format!("{} hasn't set their pronouns yet.", user.mention()),
+    };
+
+    poise::send_reply(ctx, |reply| {
+        reply
+            .content(content)
+            .ephemeral(true)
+    }).await?;
+
+    Ok(())
+}
+
+/// Normalize pronouns for comparison, so that e.g. "She / Her" matches "she/her".
+fn normalize_pronouns(pronouns: &str) -> String {
+    pronouns.to_lowercase().split_whitespace().collect::<Vec<_>>().join(" ").replace(" /", "/").replace("/ ", "/")
+}
+
+/// Give a member the roles mapped to their pronouns, and take away the ones mapped to pronouns
+/// they no longer use. Several pronouns can be set at once, separated by commas.
+async fn sync_pronoun_roles(ctx: &Context<'_>, member: &mut Member, pronoun_roles: &HashMap<String, u64>, pronouns: Option<&str>) -> Result<(), Error> {
+    let pronouns = pronouns
+        .map(|pronouns| pronouns.split(',').map(normalize_pronouns).collect::<Vec<_>>())
+        .unwrap_or_default();
+    let mut added_roles = Vec::new();
+    let mut removed_roles = Vec::new();
+
+    for (mapped_pronouns, role) in pronoun_roles {
+        let role = RoleId(*role);
+        let matches = pronouns.contains(mapped_pronouns);
+
+        if matches && !member.roles.contains(&role) && !added_roles.contains(&role) {
+            added_roles.push(role);
+        } else if !matches && member.roles.contains(&role) && !removed_roles.contains(&role) {
+            removed_roles.push(role);
+        }
+    }
+
+    // Several pronouns can share a role, which the member keeps if any of them still matches
+    removed_roles.retain(|role| !pronoun_roles.iter().any(|(mapped_pronouns, mapped_role)| RoleId(*mapped_role) == *role && pronouns.contains(mapped_pronouns)));
+
+    if !added_roles.is_empty() {
+        member.add_roles(ctx, &added_roles).await?;
+    }
+
+    if !removed_roles.is_empty() {
+        member.remove_roles(ctx, &removed_roles).await?;
+    }
+
+    Ok(())
+}
@@ -0,0 +1,250 @@
+use redis::{Commands, Connection};
+use serenity::model::guild::Role;
+use serenity::model::id::ChannelId;
+use serenity::prelude::Mentionable;
+use serenity::utils::Colour;
+use tokio::sync::MutexGuard;
+
+use crate::{Context, Error};
+use crate::{messages, utils};
+use crate::models::{RoleMenuEntry, RoleSelection};
+
+/// Maximum length of the label of a role, as Discord limits button labels to 80 characters and
+/// select menu options to 100.
+const LABEL_MAX_LENGTH: usize = 80;
+
+/// Save a role menu or role group that was just created or edited, updating its message if it
+/// was already posted.
+pub async fn save<'a, T: RoleSelection>(ctx: Context<'_>, database: &mut MutexGuard<'a, Connection>, role_selection: &T) -> Result<(), Error> {
+    let guild_id = ctx.guild_id().unwrap();
+
+    utils::save_role_selection(database, guild_id, role_selection)?;
+    messages::refresh(&ctx.serenity_context().http, database, &guild_id, &T::persistent_message(role_selection.name().to_owned())).await?;
+
+    ctx.say(format!(
+        "✅ {} `{}` saved, add roles to it using /{} add_role then post it using /{} post",
+        T::TITLE,
+        role_selection.name(),
+        T::COMMAND,
+        T::COMMAND,
+    )).await?;
+
+    Ok(())
+}
+
+/// Offer a role in a role menu or role group, or change its label and emoji
+pub async fn add_role<T: RoleSelection>(ctx: Context<'_>, name: String, role: Role, label: Option<String>, emoji: Option<String>) -> Result<(), Error> {
+    let mut database = match utils::admin_database(ctx).await? {
+        Some(database) => database,
+        None => return Ok(()),
+    };
+    let guild_id = ctx.guild_id().unwrap();
+
+    let mut role_selection: T = match existing(ctx, &mut database, &name).await? {
+        Some(role_selection) => role_selection,
+        None => return Ok(()),
+    };
+
+    if let Some(emoji) = &emoji {
+        if utils::parse_emoji(emoji).is_none() {
+            poise::send_reply(ctx, |reply| {
+                reply
+                    .content(format!("{} is not a valid emoji.", emoji))
+                    .ephemeral(true)
+            }).await?;
+
+            return Ok(());
+        }
+    }
+
+    if let Some(label) = &label {
+        if label.trim().is_empty() || label.chars().count() > LABEL_MAX_LENGTH {
+            poise::send_reply(ctx, |reply| {
+                reply
+                    .content(format!("Labels must be between 1 and {} characters long.", LABEL_MAX_LENGTH))
+                    .ephemeral(true)
+            }).await?;
+
+            return Ok(());
+        }
+    }
+
+    let entry = RoleMenuEntry {
+        role: role.id,
+        label: label.unwrap_or_else(|| utils::truncate(&role.name, LABEL_MAX_LENGTH)),
+        emoji: emoji.map(|emoji| emoji.trim().to_owned()),
+    };
+
+    let roles_count = role_selection.roles().len();
+
+    match role_selection.roles_mut().iter_mut().find(|entry| entry.role == role.id) {
+        Some(existing_entry) => *existing_entry = entry,
+        None if roles_count >= T::MAX_ROLES => {
+            poise::send_reply(ctx, |reply| {
+                reply
+                    .content(format!("{}s can offer at most {} roles.", T::TITLE, T::MAX_ROLES))
+                    .ephemeral(true)
+            }).await?;
+
+            return Ok(());
+        },
+        None => role_selection.roles_mut().push(entry),
+    }
+
+    utils::save_role_selection(&mut database, guild_id, &role_selection)?;
+    messages::refresh(&ctx.serenity_context().http, &mut database, &guild_id, &T::persistent_message(role_selection.name().to_owned())).await?;
+
+    ctx.say(format!("✅ {} `{}` now offers the {} role", T::TITLE, role_selection.name(), role)).await?;
+
+    Ok(())
+}
+
+/// Stop offering a role in a role menu or role group
+pub async fn remove_role<T: RoleSelection>(ctx: Context<'_>, name: String, role: Role) -> Result<(), Error> {
+    let mut database = match utils::admin_database(ctx).await? {
+        Some(database) => database,
+        None => return Ok(()),
+    };
+    let guild_id = ctx.guild_id().unwrap();
+
+    let mut role_selection: T = match existing(ctx, &mut database, &name).await? {
+        Some(role_selection) => role_selection,
+        None => return Ok(()),
+    };
+
+    role_selection.roles_mut().retain(|entry| entry.role != role.id);
+
+    utils::save_role_selection(&mut database, guild_id, &role_selection)?;
+    messages::refresh(&ctx.serenity_context().http, &mut database, &guild_id, &T::persistent_message(role_selection.name().to_owned())).await?;
+
+    ctx.say(format!("✅ {} `{}` no longer offers the {} role", T::TITLE, role_selection.name(), role)).await?;
+
+    Ok(())
+}
+
+/// Post a role menu or role group in the role assignment channel, or update it if it was
+/// already posted
+pub async fn post<T: RoleSelection>(ctx: Context<'_>, name: String) -> Result<(), Error> {
+    let mut database = match utils::admin_database(ctx).await? {
+        Some(database) => database,
+        None => return Ok(()),
+    };
+    let guild_id = ctx.guild_id().unwrap();
+
+    let role_selection: T = match existing(ctx, &mut database, &name).await? {
+        Some(role_selection) => role_selection,
+        None => return Ok(()),
+    };
+
+    if role_selection.roles().is_empty() {
+        poise::send_reply(ctx, |reply| {
+            reply
+                .content(format!("{} `{}` doesn't offer any role yet! Add some using /{} add_role.", T::TITLE, role_selection.name(), T::COMMAND))
+                .ephemeral(true)
+        }).await?;
+
+        return Ok(());
+    }
+
+    let role_assignment_channel = ChannelId(database.hget(format!("guild:{}", guild_id), "role_assignment_channel")?);
+
+    messages::publish(&ctx.serenity_context().http, &mut database, &guild_id, &T::persistent_message(role_selection.name().to_owned())).await?;
+
+    ctx.say(format!("📌 {} `{}` is now available in {}", T::TITLE, role_selection.name(), role_assignment_channel.mention())).await?;
+
+    Ok(())
+}
+
+/// List the role menus or role groups of this server
+pub async fn list<T: RoleSelection>(ctx: Context<'_>) -> Result<(), Error> {
+    let mut database = match utils::admin_database(ctx).await? {
+        Some(database) => database,
+        None => return Ok(()),
+    };
+    let guild_id = ctx.guild_id().unwrap();
+
+    let role_selections = utils::role_selections::<T>(&mut database, guild_id)?;
+
+    if role_selections.is_empty() {
+        poise::send_reply(ctx, |reply| {
+            reply
+                .content(format!("There are no {}s yet. Create one using /{} create.", T::KIND, T::COMMAND))
+                .ephemeral(true)
+        }).await?;
+
+        return Ok(());
+    }
+
+    let registered_messages = role_selections.iter()
+        .map(|role_selection| messages::registered_message(&mut database, &guild_id, &T::persistent_message(role_selection.name().to_owned())))
+        .collect::<Result<Vec<_>, Error>>()?;
+
+    poise::send_reply(ctx, |reply| {
+        reply
+            .embed(|embed| {
+                embed
+                    .colour(Colour::BLURPLE)
+                    .title(format!("{}s", T::TITLE));
+
+                for (role_selection, registered_message) in role_selections.iter().zip(&registered_messages).take(utils::EMBED_MAX_FIELDS) {
+                    let mut roles = if role_selection.roles().is_empty() {
+                        "No roles".to_owned()
+                    } else {
+                        role_selection.roles().iter().map(|entry| entry.role.mention().to_string()).collect::<Vec<_>>().join(", ")
+                    };
+
+                    for constraint in role_selection.constraints() {
+                        roles.push_str(&format!("\n{}", constraint));
+                    }
+
+                    let status = match registered_message {
+                        Some(registered_message) => format!("posted in {}", registered_message.channel.mention()),
+                        None => "not posted".to_owned(),
+                    };
+
+                    embed.field(format!("{} (`{}`), {}", role_selection.title(), role_selection.name(), status), roles, false);
+                }
+
+                embed
+            })
+            .ephemeral(true)
+    }).await?;
+
+    Ok(())
+}
+
+/// Delete a role menu or role group and its message
+pub async fn delete<T: RoleSelection>(ctx: Context<'_>, name: String) -> Result<(), Error> {
+    let mut database = match utils::admin_database(ctx).await? {
+        Some(database) => database,
+        None => return Ok(()),
+    };
+    let guild_id = ctx.guild_id().unwrap();
+
+    let role_selection: T = match existing(ctx, &mut database, &name).await? {
+        Some(role_selection) => role_selection,
+        None => return Ok(()),
+    };
+
+    let _: () = database.hdel(format!("{}:{}", T::KEY_PREFIX, guild_id), role_selection.name())?;
+    messages::remove(&ctx.serenity_context().http, &mut database, &guild_id, &T::persistent_message(role_selection.name().to_owned())).await?;
+
+    ctx.say(format!("🗑️ Deleted {} `{}`", T::KIND, role_selection.name())).await?;
+
+    Ok(())
+}
+
+/// Get a role menu or role group by name, or reply that it doesn't exist.
+async fn existing<'a, T: RoleSelection>(ctx: Context<'_>, database: &mut MutexGuard<'a, Connection>, name: &str) -> Result<Option<T>, Error> {
+    let role_selection = utils::role_selection(database, ctx.guild_id().unwrap(), &name.trim().to_lowercase())?;
+
+    if role_selection.is_none() {
+        poise::send_reply(ctx, |reply| {
+            reply
+                .content(format!("There is no {} named `{}`.", T::KIND, name))
+                .ephemeral(true)
+        }).await?;
+    }
+
+    Ok(role_selection)
+}
@@ -0,0 +1,111 @@
+use serenity::model::guild::Role;
+
+use crate::{Context, Error};
+use crate::utils;
+use crate::models::{RoleGroup, RoleSelection};
+use super::role_selection;
+
+/// Let members pick roles from groups with constraints, e.g. a single region
+#[poise::command(
+    slash_command,
+    subcommands("create", "add_role", "remove_role", "post", "list", "delete"),
+)]
+pub async fn rolegroup(_: Context<'_>) -> Result<(), Error> {
+    Ok(())
+}
+
+/// Create a role group, or change the title, description and constraints of an existing one
+#[poise::command(slash_command)]
+async fn create(
+    ctx: Context<'_>,
+    #[description = "Short name identifying the group, e.g. regions."] name: String,
+    #[description = "The title of the group."] title: String,
+    #[description = "The description of the group."] description: Option<String>,
+    #[description = "Whether members can only have one role of the group, picking another one replaces it."] single_choice: Option<bool>,
+    #[description = "Maximum number of roles of the group a member can have, 0 for no limit."] max_roles: Option<u64>,
+    #[description = "Whether only validated members can pick roles of the group."] validated_only: Option<bool>,
+) -> Result<(), Error> {
+    let mut database = match utils::admin_database(ctx).await? {
+        Some(database) => database,
+        None => return Ok(()),
+    };
+    let guild_id = ctx.guild_id().unwrap();
+
+    let name = match utils::validated_name(ctx, RoleGroup::TITLE, &name).await? {
+        Some(name) => name,
+        None => return Ok(()),
+    };
+
+    let role_group = match utils::role_selection::<RoleGroup>(&mut database, guild_id, &name)? {
+        Some(mut role_group) => {
+            role_group.title = title;
+            role_group.description = description;
+            role_group.single_choice = single_choice.unwrap_or(role_group.single_choice);
+
+            if let Some(max_roles) = max_roles {
+                role_group.max_roles = Some(max_roles).filter(|max_roles| *max_roles > 0);
+            }
+
+            role_group.validated_only = validated_only.unwrap_or(role_group.validated_only);
+
+            role_group
+        },
+        None => RoleGroup {
+            name,
+            title,
+            description,
+            single_choice: single_choice.unwrap_or_default(),
+            max_roles: max_roles.filter(|max_roles| *max_roles > 0),
+            validated_only: validated_only.unwrap_or_default(),
+            roles: Vec::new(),
+        },
+    };
+
+    role_selection::save(ctx, &mut database, &role_group).await
+}
+
+/// Offer a role in a role group
+#[poise::command(slash_command)]
+async fn add_role(
+    ctx: Context<'_>,
+    #[description = "The name of the group."] group: String,
+    #[description = "The role to offer."] role: Role,
+    #[description = "The label of the role's button or option, the role name by default."] label: Option<String>,
+    #[description = "The emoji of the role's button or option."] emoji: Option<String>,
+) -> Result<(), Error> {
+    role_selection::add_role::<RoleGroup>(ctx, group, role, label, emoji).await
+}
+
+/// Stop offering a role in a role group
+#[poise::command(slash_command)]
+async fn remove_role(
+    ctx: Context<'_>,
+    #[description = "The name of the group."] group: String,
+    #[description = "The role to stop offering."] role: Role,
+) -> Result<(), Error> {
+    role_selection::remove_role::<RoleGroup>(ctx, group, role).await
+}
+
+/// Post a role group in the role assignment channel, or update it if it was already posted
+#[poise::command(slash_command)]
+async fn post(
+    ctx: Context<'_>,
+    #[description = "The name of the group."] group: String,
+) -> Result<(), Error> {
+    role_selection::post::<RoleGroup>(ctx, group).await
+}
+
+/// List the role groups of this server
+#[poise::command(slash_command)]
+async fn list(ctx: Context<'_>) -> Result<(), Error> {
+    role_selection::list::<RoleGroup>(ctx).await
+}
+
+/// Delete a role group and its message
+#[poise::command(slash_command)]
+async fn delete(
+    ctx: Context<'_>,
+    #[description = "The name of the group."] group: String,
+) -> Result<(), Error> {
+    role_selection::delete::<RoleGroup>(ctx, group).await
+}
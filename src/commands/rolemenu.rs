@@ -0,0 +1,101 @@
+use serenity::model::guild::Role;
+
+use crate::{Context, Error};
+use crate::utils;
+use crate::models::{RoleMenu, RoleMenuStyle, RoleSelection};
+use super::role_selection;
+
+/// Let members pick their own roles in the role assignment channel
+#[poise::command(
+    slash_command,
+    subcommands("create", "add_role", "remove_role", "post", "list", "delete"),
+)]
+pub async fn rolemenu(_: Context<'_>) -> Result<(), Error> {
+    Ok(())
+}
+
+/// Create a role menu, or change the title, description and style of an existing one
+#[poise::command(slash_command)]
+async fn create(
+    ctx: Context<'_>,
+    #[description = "Short name identifying the menu, e.g. colours."] name: String,
+    #[description = "The title of the menu."] title: String,
+    #[description = "The description of the menu."] description: Option<String>,
+    #[description = "Whether roles are offered as buttons or in a select menu."] style: Option<RoleMenuStyle>,
+) -> Result<(), Error> {
+    let mut database = match utils::admin_database(ctx).await? {
+        Some(database) => database,
+        None => return Ok(()),
+    };
+    let guild_id = ctx.guild_id().unwrap();
+
+    let name = match utils::validated_name(ctx, RoleMenu::TITLE, &name).await? {
+        Some(name) => name,
+        None => return Ok(()),
+    };
+
+    let role_menu = match utils::role_selection::<RoleMenu>(&mut database, guild_id, &name)? {
+        Some(mut role_menu) => {
+            role_menu.title = title;
+            role_menu.description = description;
+            role_menu.style = style.unwrap_or(role_menu.style);
+
+            role_menu
+        },
+        None => RoleMenu {
+            name,
+            title,
+            description,
+            style: style.unwrap_or_default(),
+            roles: Vec::new(),
+        },
+    };
+
+    role_selection::save(ctx, &mut database, &role_menu).await
+}
+
+/// Offer a role in a role menu
+#[poise::command(slash_command)]
+async fn add_role(
+    ctx: Context<'_>,
+    #[description = "The name of the menu."] menu: String,
+    #[description = "The role to offer."] role: Role,
+    #[description = "The label of the role's button or option, the role name by default."] label: Option<String>,
+    #[description = "The emoji of the role's button or option."] emoji: Option<String>,
+) -> Result<(), Error> {
+    role_selection::add_role::<RoleMenu>(ctx, menu, role, label, emoji).await
+}
+
+/// Stop offering a role in a role menu
+#[poise::command(slash_command)]
+async fn remove_role(
+    ctx: Context<'_>,
+    #[description = "The name of the menu."] menu: String,
+    #[description = "The role to stop offering."] role: Role,
+) -> Result<(), Error> {
+    role_selection::remove_role::<RoleMenu>(ctx, menu, role).await
+}
+
+/// Post a role menu in the role assignment channel, or update it if it was already posted
+#[poise::command(slash_command)]
+async fn post(
+    ctx: Context<'_>,
+    #[description = "The name of the menu."] menu: String,
+) -> Result<(), Error> {
+    role_selection::post::<RoleMenu>(ctx, menu).await
+}
+
+/// List the role menus of this server
+#[poise::command(slash_command)]
+async fn list(ctx: Context<'_>) -> Result<(), Error> {
+    role_selection::list::<RoleMenu>(ctx).await
+}
+
+/// Delete a role menu and its message
+#[poise::command(slash_command)]
+async fn delete(
+    ctx: Context<'_>,
+    #[description = "The name of the menu."] menu: String,
+) -> Result<(), Error> {
+    role_selection::delete::<RoleMenu>(ctx, menu).await
+}
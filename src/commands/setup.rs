@@ -1,10 +1,11 @@
 use redis::Commands;
 use serenity::model::channel::Channel;
 use serenity::model::guild::Role;
-use tracing::error;
+use tracing::{error, warn};
 
 use crate::{Context, Error};
-use crate::models::GuildConfiguration;
+use crate::{events, messages, utils};
+use crate::models::{GuildConfiguration, PersistentMessage};
 
 /// Configure Poucet to serve a guild
 #[poise::command(slash_command)]
@@ -16,6 +17,7 @@ pub async fn setup(
     #[description = "Channel in which members can assign themselves roles"] role_assignment_channel: Channel,
     #[description = "Category in which to create private channels for member validation"] validation_category: Channel,
     #[description = "Channel in which to post a welcome message for new members"] welcome_channel: Channel,
+    #[description = "Staff channel in which to post alerts and logs, the current one is kept if empty"] log_channel: Option<Channel>,
     #[description = "Run setup for an already configured guild"] anew: Option<bool>,
 ) -> Result<(), Error> {
     let bot = ctx.data();
@@ -32,7 +34,7 @@ pub async fn setup(
         return Ok(());
     }
 
-    let guild_configuration = GuildConfiguration::new(admin_role, validated_role, introductions_channel, role_assignment_channel, validation_category, welcome_channel)
+    let guild_configuration = GuildConfiguration::new(admin_role, validated_role, introductions_channel, role_assignment_channel, validation_category, welcome_channel, log_channel)
         .unwrap_or_else(|error| {
             error!("incorrect server configuration values: {}", error);
 
@@ -50,17 +52,44 @@ pub async fn setup(
     }
 
     let guild_key = format!("guild:{}", guild_id);
+    let introductions_channel = guild_configuration.introductions_channel.unwrap().id;
     let mut database = bot.database.lock().await;
 
-    database.hset(&guild_key, "configured", guild_configuration.configured)?;
-    database.hset(&guild_key, "admin_role", guild_configuration.admin_role.unwrap().id.as_u64())?;
-    database.hset(&guild_key, "validated_role", guild_configuration.validated_role.unwrap().id.as_u64())?;
-    database.hset(&guild_key, "introductions_channel", guild_configuration.introductions_channel.unwrap().id.as_u64())?;
-    database.hset(&guild_key, "role_assignment_channel", guild_configuration.role_assignment_channel.unwrap().id.as_u64())?;
-    database.hset(&guild_key, "validation_category", guild_configuration.validation_category.unwrap().id.as_u64())?;
-    database.hset(&guild_key, "welcome_channel", guild_configuration.welcome_channel.unwrap().id.as_u64())?;
+    let _: () = database.hset(&guild_key, "configured", guild_configuration.configured)?;
+    let _: () = database.hset(&guild_key, "admin_role", guild_configuration.admin_role.unwrap().id.as_u64())?;
+    let _: () = database.hset(&guild_key, "validated_role", guild_configuration.validated_role.unwrap().id.as_u64())?;
+    let _: () = database.hset(&guild_key, "introductions_channel", introductions_channel.as_u64())?;
+    let _: () = database.hset(&guild_key, "role_assignment_channel", guild_configuration.role_assignment_channel.unwrap().id.as_u64())?;
+    let _: () = database.hset(&guild_key, "validation_category", guild_configuration.validation_category.unwrap().id.as_u64())?;
+    let _: () = database.hset(&guild_key, "welcome_channel", guild_configuration.welcome_channel.unwrap().id.as_u64())?;
 
-    ctx.say("🙌 All set! Poucet is now ready to use 🤖✨").await?;
+    // Running the setup again without a log channel keeps the current one, it can be removed
+    // using /config set log_channel
+    if let Some(log_channel) = guild_configuration.log_channel {
+        let _: () = database.hset(&guild_key, "log_channel", log_channel.id.as_u64())?;
+    }
+
+    bot.set_introductions_channel(guild_id, introductions_channel).await;
+
+    if let Err(error) = events::cache_invites(&ctx.serenity_context().http, &mut database, &guild_id).await {
+        warn!("could not cache invites of guild {}, new members' invites won't be tracked: {}", guild_id, error);
+    }
+
+    // Post the welcome message right away, or move it if the welcome channel changed. Missing
+    // permissions are reported by the configuration check below rather than failing the setup.
+    if let Err(error) = messages::publish(&ctx.serenity_context().http, &mut database, &guild_id, &PersistentMessage::Welcome).await {
+        warn!("could not post the welcome message of guild {}: {}", guild_id, error);
+    }
+
+    let guild = ctx.guild().unwrap();
+    let bot_member = guild.member(&ctx, ctx.serenity_context().cache.current_user_id()).await?;
+    let problems = utils::configuration_problems(&ctx.serenity_context().cache, &mut database, &guild, &bot_member)?;
+
+    poise::send_reply(ctx, |reply| {
+        reply
+            .content("🙌 All set! Poucet is now ready to use 🤖✨")
+            .embed(|embed| utils::configuration_problems_embed(&problems, embed))
+    }).await?;
 
     Ok(())
 }
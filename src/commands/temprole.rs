@@ -0,0 +1,127 @@
+use redis::Commands;
+use serenity::model::guild::{Member, Role};
+use serenity::model::id::RoleId;
+use serenity::model::Timestamp;
+use serenity::prelude::Mentionable;
+
+use crate::{Context, Error};
+use crate::{tasks, utils};
+
+/// Give a member a role for a limited time
+#[poise::command(slash_command)]
+pub async fn temprole(
+    ctx: Context<'_>,
+    #[description = "The member to give the role to."] member: Member,
+    #[description = "The role to give."] role: Role,
+    #[description = "How long the member keeps the role, e.g. 1h30m, 3d or 2w."] duration: String,
+) -> Result<(), Error> {
+    let bot = ctx.data();
+    let guild_id = ctx.guild_id().unwrap();
+    let serves_guild = bot.serves_guild(guild_id).await?;
+
+    if !serves_guild {
+        poise::send_reply(ctx, |reply| {
+            reply
+                .content("I am not configured to work on this server! Have an admin configure me using the /setup command.")
+                .ephemeral(true)
+        }).await?;
+
+        return Ok(());
+    }
+
+    let mut database = bot.database.lock().await;
+
+    if !utils::is_staff(ctx, &mut database, guild_id).await? {
+        poise::send_reply(ctx, |reply| {
+            reply
+                .content("This is a staff command, you do not have the required rights to run it!")
+                .ephemeral(true)
+        }).await?;
+
+        return Ok(());
+    }
+
+    let guild_key = format!("guild:{}", guild_id);
+    let admin_role: u64 = database.hget(&guild_key, "admin_role")?;
+    let validated_role: u64 = database.hget(&guild_key, "validated_role")?;
+
+    // Access to the server and to admin commands must not bypass onboarding and appointment
+    if role.id == RoleId(admin_role) || role.id == RoleId(validated_role) {
+        poise::send_reply(ctx, |reply| {
+            reply
+                .content(format!("{} can't be given temporarily.", role))
+                .ephemeral(true)
+        }).await?;
+
+        return Ok(());
+    }
+
+    // Staff can't hand out more rights than their own
+    if ctx.author().id != ctx.guild().unwrap().owner_id {
+        let author = guild_id.member(&ctx, ctx.author().id).await?;
+        let author_position = author.highest_role_info(&ctx.serenity_context().cache).map(|(_, position)| position).unwrap_or_default();
+
+        if role.position >= author_position {
+            poise::send_reply(ctx, |reply| {
+                reply
+                    .content(format!("You can only give roles below your highest role, and {} isn't.", role))
+                    .ephemeral(true)
+            }).await?;
+
+            return Ok(());
+        }
+    }
+
+    let expires_at = utils::parse_duration(&duration)
+        .and_then(|duration| Timestamp::now().unix_timestamp().checked_add(duration))
+        .filter(|expires_at| Timestamp::from_unix_timestamp(*expires_at).is_ok());
+    let expires_at = match expires_at {
+        Some(expires_at) => expires_at,
+        None => {
+            poise::send_reply(ctx, |reply| {
+                reply
+                    .content(format!("`{}` is not a valid duration, use e.g. 1h30m, 3d or 2w.", duration))
+                    .ephemeral(true)
+            }).await?;
+
+            return Ok(());
+        },
+    };
+
+    // Giving the role again to a member who already has it temporarily replaces its expiry, but a
+    // role they hold permanently must not be scheduled for removal
+    let temporary_role_key = tasks::temporary_role_key(guild_id, member.user.id, role.id);
+    let current_expiry: Option<i64> = database.zscore(tasks::TEMPORARY_ROLES_KEY, &temporary_role_key)?;
+
+    if member.roles.contains(&role.id) && current_expiry.is_none() {
+        poise::send_reply(ctx, |reply| {
+            reply
+                .content(format!("{} already has {} permanently.", member, role))
+                .ephemeral(true)
+        }).await?;
+
+        return Ok(());
+    }
+
+    let reason = format!("Temporary role given by {} until {}", utils::tag(&ctx.author().name, ctx.author().discriminator), Timestamp::from_unix_timestamp(expires_at)?);
+
+    ctx.serenity_context().http.add_member_role(guild_id.0, member.user.id.0, role.id.0, Some(&reason)).await?;
+
+    let _: () = database.zadd(tasks::TEMPORARY_ROLES_KEY, &temporary_role_key, expires_at)?;
+
+    utils::log_to_staff(&ctx, &mut database, guild_id, format!(
+        "⏳ {} gave {} to {} until <t:{}:f>",
+        ctx.author().mention(),
+        role.mention(),
+        member.mention(),
+        expires_at,
+    )).await?;
+
+    poise::send_reply(ctx, |reply| {
+        reply
+            .content(format!("⏳ Gave {} to {} until <t:{}:f>", role, member, expires_at))
+            .ephemeral(true)
+    }).await?;
+
+    Ok(())
+}
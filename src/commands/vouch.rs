@@ -0,0 +1,103 @@
+use redis::Commands;
+use serenity::model::guild::Member;
+use serenity::model::id::RoleId;
+use serenity::prelude::Mentionable;
+
+use crate::{Context, Error};
+use crate::{events, utils};
+use crate::models::{HistoryEntry, HistoryEvent};
+
+/// Vouch for a member applying to join the server
+#[poise::command(slash_command)]
+pub async fn vouch(
+    ctx: Context<'_>,
+    #[description = "The member to vouch for."] member: Member,
+) -> Result<(), Error> {
+    let bot = ctx.data();
+    let guild_id = ctx.guild_id().unwrap();
+    let serves_guild = bot.serves_guild(guild_id).await?;
+
+    if !serves_guild {
+        poise::send_reply(ctx, |reply| {
+            reply
+                .content("I am not configured to work on this server! Have an admin configure me using the /setup command.")
+                .ephemeral(true)
+        }).await?;
+
+        return Ok(());
+    }
+
+    let onboarding_key = format!("onboarding:{}", guild_id);
+    let mut database = bot.database.lock().await;
+
+    let trusted_role: Option<u64> = database.hget(&onboarding_key, "trusted_role")?;
+    let trusted_role = match trusted_role {
+        Some(trusted_role) => RoleId(trusted_role),
+        None => {
+            poise::send_reply(ctx, |reply| {
+                reply
+                    .content("Vouching is not enabled on this server! Have an admin configure a trusted role using the /onboarding trusted_role command.")
+                    .ephemeral(true)
+            }).await?;
+
+            return Ok(());
+        },
+    };
+
+    if !ctx.author().has_role(&ctx, guild_id, trusted_role).await? {
+        poise::send_reply(ctx, |reply| {
+            reply
+                .content(format!("Only members with the {} role can vouch for applicants!", trusted_role.mention()))
+                .ephemeral(true)
+        }).await?;
+
+        return Ok(());
+    }
+
+    if member.user.id == ctx.author().id {
+        poise::send_reply(ctx, |reply| {
+            reply
+                .content("You can't vouch for yourself!")
+                .ephemeral(true)
+        }).await?;
+
+        return Ok(());
+    }
+
+    let vouches_key = format!("vouches:{}:{}", guild_id, member.user.id);
+    let added: u64 = database.sadd(&vouches_key, ctx.author().id.as_u64())?;
+
+    if added == 0 {
+        poise::send_reply(ctx, |reply| {
+            reply
+                .content(format!("You already vouched for {}!", member.mention()))
+                .ephemeral(true)
+        }).await?;
+
+        return Ok(());
+    }
+
+    utils::record_history(&mut database, guild_id, member.user.id, &HistoryEntry::new(HistoryEvent::Vouch, Some(ctx.author().id), None))?;
+
+    let vouches: u64 = database.scard(&vouches_key)?;
+    let announcement = format!(
+        "🤝 {} vouched for {} ({} vouch(es) in total)",
+        ctx.author().mention(),
+        member.mention(),
+        vouches,
+    );
+
+    if let Some(validation_channel) = events::pending_validation(&mut database, &guild_id, &member.user.id).await? {
+        validation_channel.say(&ctx, &announcement).await?;
+    }
+
+    utils::log_to_staff(&ctx, &mut database, guild_id, &announcement).await?;
+
+    poise::send_reply(ctx, |reply| {
+        reply
+            .content(format!("🤝 Thanks for vouching for {}! Staff will take it into account.", member.mention()))
+            .ephemeral(true)
+    }).await?;
+
+    Ok(())
+}
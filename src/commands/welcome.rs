@@ -0,0 +1,65 @@
+use redis::Commands;
+use serenity::model::id::ChannelId;
+use serenity::prelude::Mentionable;
+
+use crate::{Context, Error};
+use crate::{messages, utils};
+use crate::models::PersistentMessage;
+
+/// Manage the welcome message new members start onboarding from
+#[poise::command(
+    slash_command,
+    subcommands("post", "refresh"),
+)]
+pub async fn welcome(_: Context<'_>) -> Result<(), Error> {
+    Ok(())
+}
+
+/// Post the welcome message again at the bottom of the welcome channel
+#[poise::command(slash_command)]
+async fn post(ctx: Context<'_>) -> Result<(), Error> {
+    let mut database = match utils::admin_database(ctx).await? {
+        Some(database) => database,
+        None => return Ok(()),
+    };
+    let guild_id = ctx.guild_id().unwrap();
+    let guild_key = format!("guild:{}", guild_id);
+
+    let http = &ctx.serenity_context().http;
+
+    messages::remove(http, &mut database, &guild_id, &PersistentMessage::Welcome).await?;
+    messages::publish(http, &mut database, &guild_id, &PersistentMessage::Welcome).await?;
+
+    let welcome_channel = database.hget(&guild_key, "welcome_channel")?;
+    let welcome_channel = ChannelId(welcome_channel);
+
+    ctx.say(format!("👋 Posted the welcome message in {}", welcome_channel.mention())).await?;
+
+    Ok(())
+}
+
+/// Update the welcome message in place from the current template
+#[poise::command(slash_command)]
+async fn refresh(ctx: Context<'_>) -> Result<(), Error> {
+    let mut database = match utils::admin_database(ctx).await? {
+        Some(database) => database,
+        None => return Ok(()),
+    };
+    let guild_id = ctx.guild_id().unwrap();
+
+    if messages::registered_message(&mut database, &guild_id, &PersistentMessage::Welcome)?.is_none() {
+        poise::send_reply(ctx, |reply| {
+            reply
+                .content("The welcome message isn't posted yet. Post it using /welcome post.")
+                .ephemeral(true)
+        }).await?;
+
+        return Ok(());
+    }
+
+    messages::publish(&ctx.serenity_context().http, &mut database, &guild_id, &PersistentMessage::Welcome).await?;
+
+    ctx.say("👋 The welcome message is up to date").await?;
+
+    Ok(())
+}
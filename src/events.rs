@@ -1,37 +1,93 @@
+use std::collections::HashMap;
+
 use redis::{Commands, Connection};
-use serenity::builder::{CreateMessage, CreateInteractionResponseFollowup};
-use serenity::http::StatusCode;
+use serenity::builder::{CreateEmbed, CreateMessage, CreateInteractionResponseFollowup};
+use serenity::http::{Http, StatusCode};
 use serenity::model::application::component::ButtonStyle;
 use serenity::model::application::interaction::{Interaction, InteractionResponseType};
 use serenity::model::application::interaction::message_component::MessageComponentInteraction;
-use serenity::model::channel::{ChannelType, PermissionOverwrite, PermissionOverwriteType};
+use serenity::model::channel::{Channel, ChannelType, Message, MessageType, PermissionOverwrite, PermissionOverwriteType};
+use serenity::model::event::{InviteCreateEvent, InviteDeleteEvent};
 use serenity::model::gateway::Ready;
 use serenity::model::guild::{Guild, Member, Role};
 use serenity::model::id::{ChannelId, GuildId, RoleId, UserId};
 use serenity::model::permissions::Permissions;
+use serenity::model::Timestamp;
 use serenity::model::user::User;
 use serenity::prelude::{Mentionable, SerenityError};
 use serenity::utils::Colour;
 use tokio::sync::MutexGuard;
-use tracing::{debug, error, info};
+use tracing::{debug, error, info, warn};
 
 use crate::{Bot, Error};
-use crate::identifiers;
+use crate::{identifiers, messages, utils};
+use crate::models::{HistoryEntry, HistoryEvent, InviteConversion, Note, OnboardingMode, OnboardingPause, PersistentMessage, RoleGroup, RoleMenu};
+
+/// Sorted set of guilds whose applications are closed until a given time, scored by that time.
+pub const SCHEDULED_RESUMES_KEY: &str = "scheduled_onboarding_resumes";
+
+/// Sorted set of approved members to remind to introduce themselves, as `guild:user` members
+/// scored by the time of the reminder.
+pub const SCHEDULED_INTRODUCTION_REMINDERS_KEY: &str = "scheduled_introduction_reminders";
+
+/// Maximum length of a thread name, as enforced by Discord.
+const THREAD_NAME_MAX_LENGTH: usize = 100;
+
+/// Default time window over which joins and applications are counted for raid protection, in seconds.
+const DEFAULT_RAID_WINDOW: i64 = 60;
+
+/// Kinds of activity tracked for raid protection.
+#[derive(Clone, Copy)]
+enum RaidActivity {
+    Join,
+    Application,
+}
+
+impl RaidActivity {
+    fn name(&self) -> &'static str {
+        match self {
+            Self::Join => "join",
+            Self::Application => "application",
+        }
+    }
+
+    fn threshold_field(&self) -> &'static str {
+        match self {
+            Self::Join => "raid_max_joins",
+            Self::Application => "raid_max_applications",
+        }
+    }
+}
+
+/// Information about an applicant shown to staff when they open a validation channel.
+struct ApplicantSummary {
+    rules_acceptance: Option<(String, i64)>,
+    previous_applications: usize,
+    denials: usize,
+    notes: usize,
+    vouches: usize,
+    invite: Option<String>,
+    flags: Vec<String>,
+}
 
 // Event dispatcher
 pub async fn listener(ctx: &serenity::client::Context, event: &poise::Event<'_>, _framework: poise::FrameworkContext<'_, Bot, Error>, bot: &Bot) -> Result<(), Error> {
     match event {
         poise::Event::Ready { data_about_bot } => ready(data_about_bot),
         poise::Event::GuildCreate { guild, is_new } => guild_create(ctx, bot, guild, is_new).await,
+        poise::Event::GuildMemberAddition { new_member } => guild_member_addition(ctx, bot, new_member).await,
+        poise::Event::InviteCreate { data } => invite_create(bot, data).await,
+        poise::Event::InviteDelete { data } => invite_delete(bot, data).await,
         poise::Event::GuildMemberRemoval { guild_id, user, member_data_if_available: _ } => guild_member_removal(ctx, bot, guild_id, user).await,
         poise::Event::InteractionCreate { interaction } => interaction_create(ctx, bot, interaction).await,
+        poise::Event::Message { new_message } => message(ctx, bot, new_message).await,
         _ => Ok(()),
     }
 }
 
 // Event handlers
 fn ready(data: &Ready) -> Result<(), Error> {
-    info!("Authenticated as {}#{}", data.user.name, data.user.discriminator);
+    info!("Authenticated as {}", utils::tag(&data.user.name, data.user.discriminator));
 
     Ok(())
 }
@@ -49,40 +105,59 @@ async fn guild_create(ctx: &serenity::client::Context, bot: &Bot, guild: &Guild,
         return Ok(());
     }
 
-    let guild_key = format!("guild:{}", guild.id);
     let mut database = bot.database.lock().await;
-    let welcome_channel = database.hget(&guild_key, "welcome_channel")?;
-    let welcome_channel = ChannelId(welcome_channel);
 
-    if database.hexists(&guild_key, "welcome_message")? {
-        let welcome_message: u64 = database.hget(&guild_key, "welcome_message")?;
-        let welcome_message = welcome_channel.message(&ctx.http, welcome_message).await;
+    if let Err(error) = cache_invites(&ctx.http, &mut database, &guild.id).await {
+        warn!("could not cache invites of guild {}, new members' invites won't be tracked: {}", guild.id, error);
+    }
 
-        if welcome_message.is_ok() {
-            return Ok(());
-        }
+    messages::verify_persistent_messages(&ctx.http, &mut database, &guild.id).await?;
+
+    Ok(())
+}
 
-        let error = welcome_message.err().unwrap();
+async fn invite_create(bot: &Bot, invite: &InviteCreateEvent) -> Result<(), Error> {
+    let guild_id = match invite.guild_id {
+        Some(guild_id) => guild_id,
+        None => return Ok(()),
+    };
 
-        if let SerenityError::Http(error) = error {
-            if let Some(status_code) = error.status_code() {
-                if status_code == StatusCode::NOT_FOUND {
-                    let new_welcome_message = welcome_channel.send_message(&ctx.http, welcome_instructions).await?;
+    if !bot.serves_guild(guild_id).await? {
+        return Ok(());
+    }
 
-                    database.hset(&guild_key, "welcome_message", new_welcome_message.id.as_u64())?;
+    let mut database = bot.database.lock().await;
 
-                    return Ok(());
-                }
-            }
+    let _: () = database.hset(format!("invites:{}", guild_id), &invite.code, 0)?;
 
-            error!("{}", error);
-        }
-    } else {
-        let new_welcome_message = welcome_channel.send_message(&ctx.http, welcome_instructions).await?;
+    if let Some(inviter) = &invite.inviter {
+        let _: () = database.hset(format!("invite_inviters:{}", guild_id), &invite.code, inviter.id.as_u64())?;
+    }
+
+    Ok(())
+}
 
-        database.hset(&guild_key, "welcome_message", new_welcome_message.id.as_u64())?;
+async fn invite_delete(bot: &Bot, invite: &InviteDeleteEvent) -> Result<(), Error> {
+    let guild_id = match invite.guild_id {
+        Some(guild_id) => guild_id,
+        None => return Ok(()),
+    };
+
+    if !bot.serves_guild(guild_id).await? {
+        return Ok(());
     }
 
+    let mut database = bot.database.lock().await;
+
+    // Inviters and labels are kept for the conversion report
+    let _: () = database.hdel(format!("invites:{}", guild_id), &invite.code)?;
+
+    Ok(())
+}
+
+async fn guild_member_addition(ctx: &serenity::client::Context, bot: &Bot, member: &Member) -> Result<(), Error> {
+    onboarding_member_addition(ctx, bot, member).await?;
+
     Ok(())
 }
 
@@ -94,6 +169,11 @@ async fn guild_member_removal(ctx: &serenity::client::Context, bot: &Bot, guild_
 
 async fn interaction_create(ctx: &serenity::client::Context, bot: &Bot, interaction: &Interaction) -> Result<(), Error> {
     if let Interaction::MessageComponent(interaction) = interaction {
+        // Guides are browsed in direct messages, where interactions don't have a guild
+        if interaction.data.custom_id.starts_with(identifiers::GUIDE) {
+            return guide_navigation(ctx, bot, interaction).await;
+        }
+
         let guild_id = interaction.guild_id.unwrap();
         let serves_guild = bot.serves_guild(guild_id).await?;
 
@@ -102,9 +182,13 @@ async fn interaction_create(ctx: &serenity::client::Context, bot: &Bot, interact
         }
 
         match interaction.data.custom_id.as_str() {
+            identifiers::ONBOARDING_ACCEPT_RULES => onboarding_accept_rules(ctx, bot, interaction).await?,
+            identifiers::ONBOARDING_APPROVAL_ROLES => onboarding_approval_roles(ctx, bot, interaction).await?,
             identifiers::ONBOARDING_ARCHIVE => onboarding_archive(ctx, bot, interaction).await?,
             identifiers::ONBOARDING_DELETE => onboarding_delete(ctx, bot, interaction).await?,
             identifiers::ONBOARDING_START => onboarding_start(ctx, bot, interaction).await?,
+            custom_id if custom_id.starts_with(identifiers::ROLE_MENU) => role_menu_selection(ctx, bot, interaction).await?,
+            custom_id if custom_id.starts_with(identifiers::ROLE_GROUP) => role_group_selection(ctx, bot, interaction).await?,
             _ => (),
         }
     }
@@ -112,7 +196,137 @@ async fn interaction_create(ctx: &serenity::client::Context, bot: &Bot, interact
     Ok(())
 }
 
+async fn message(ctx: &serenity::client::Context, bot: &Bot, message: &Message) -> Result<(), Error> {
+    let guild_id = match message.guild_id {
+        Some(guild_id) => guild_id,
+        None => return Ok(()),
+    };
+
+    if message.author.bot || message.kind != MessageType::Regular {
+        return Ok(());
+    }
+
+    // Every message posted in the guild ends up here, so messages outside of the introductions
+    // channel are filtered out using the cached channel without locking the database
+    if bot.introductions_channel(guild_id).await? != Some(message.channel_id) {
+        return Ok(());
+    }
+
+    let serves_guild = bot.serves_guild(guild_id).await?;
+
+    if !serves_guild {
+        return Ok(());
+    }
+
+    introduction_post(ctx, bot, &guild_id, message).await
+}
+
 // Onboarding actions
+async fn onboarding_accept_rules(ctx: &serenity::client::Context, bot: &Bot, interaction: &MessageComponentInteraction) -> Result<(), Error> {
+    let guild_id = interaction.guild_id.unwrap();
+    let user_id = interaction.user.id;
+    let onboarding_key = format!("onboarding:{}", guild_id);
+    let validation_key = format!("validation:{}:{}", guild_id, user_id);
+
+    {
+        let mut database = bot.database.lock().await;
+        let rules_version: Option<String> = database.hget(&onboarding_key, "rules_version")?;
+
+        let _: () = database.hset(&validation_key, "rules_version", rules_version.unwrap_or_else(|| "1".into()))?;
+        let _: () = database.hset(&validation_key, "rules_accepted_at", Timestamp::now().unix_timestamp())?;
+    }
+
+    info!("member {} accepted the rules in guild {}", user_id, guild_id);
+
+    onboarding_start(ctx, bot, interaction).await
+}
+
+async fn onboarding_approval_roles(ctx: &serenity::client::Context, bot: &Bot, interaction: &MessageComponentInteraction) -> Result<(), Error> {
+    let guild_id = interaction.guild_id.unwrap();
+    let validation_channel_to_user_key = "validation_channel_to_user";
+    let mut database = bot.database.lock().await;
+
+    if !utils::member_is_staff(&ctx.cache, &mut database, interaction.member.as_ref().unwrap())? {
+        interaction.create_interaction_response(&ctx.http, |response| {
+            response
+                .kind(InteractionResponseType::ChannelMessageWithSource)
+                .interaction_response_data(|data| {
+                    data
+                        .content("Only staff members can grant roles to approved members!")
+                        .ephemeral(true)
+                })
+        }).await?;
+
+        return Ok(());
+    }
+
+    let user_id: Option<u64> = database.hget(validation_channel_to_user_key, interaction.channel_id.as_u64())?;
+
+    let user_id = match user_id {
+        Some(user_id) => UserId(user_id),
+        None => {
+            interaction.create_interaction_response(&ctx.http, |response| {
+                response
+                    .kind(InteractionResponseType::ChannelMessageWithSource)
+                    .interaction_response_data(|data| {
+                        data
+                            .content("This validation channel is no longer managed by me, please grant roles manually.")
+                            .ephemeral(true)
+                    })
+            }).await?;
+
+            return Ok(());
+        },
+    };
+
+    // Only grant roles that are still offered, in case the configuration changed since approval
+    let approval_roles: Vec<u64> = database.smembers(format!("approval_roles:{}", guild_id))?;
+    let roles = interaction.data.values.iter()
+        .filter_map(|role| role.parse().ok())
+        .filter(|role| approval_roles.contains(role))
+        .map(RoleId)
+        .collect::<Vec<_>>();
+
+    if roles.is_empty() {
+        interaction.create_interaction_response(&ctx.http, |response| {
+            response
+                .kind(InteractionResponseType::ChannelMessageWithSource)
+                .interaction_response_data(|data| {
+                    data
+                        .content("None of the selected roles are offered upon approval anymore.")
+                        .ephemeral(true)
+                })
+        }).await?;
+
+        return Ok(());
+    }
+
+    let mut member = guild_id.member(&ctx.http, user_id).await?;
+
+    member.add_roles(&ctx.http, &roles).await?;
+
+    let roles = roles.iter().map(|role| role.mention().to_string()).collect::<Vec<_>>().join(", ");
+
+    info!("{} granted roles {:?} to member {} in guild {}", interaction.user.id, interaction.data.values, user_id, guild_id);
+
+    interaction.create_interaction_response(&ctx.http, |response| {
+        response
+            .kind(InteractionResponseType::ChannelMessageWithSource)
+            .interaction_response_data(|data| {
+                data.content(format!("🎭 {} granted {} to {}", interaction.user.mention(), roles, member.mention()))
+            })
+    }).await?;
+
+    utils::log_to_staff(&ctx.http, &mut database, guild_id, format!(
+        "🎭 {} granted {} to {} upon approval",
+        interaction.user.mention(),
+        roles,
+        member.mention(),
+    )).await?;
+
+    Ok(())
+}
+
 async fn onboarding_archive(ctx: &serenity::client::Context, bot: &Bot, interaction: &MessageComponentInteraction) -> Result<(), Error> {
     interaction.create_interaction_response(&ctx.http, |response| {
         response
@@ -136,17 +350,23 @@ async fn onboarding_archive(ctx: &serenity::client::Context, bot: &Bot, interact
         database.hdel(&validation_user_to_channel_key, user_id.as_u64())?;
         database.hdel(&validation_channel_to_user_key, validation_channel.as_u64())?;
 
-        let mut guild_channels = guild_id.channels(&ctx.http).await?;
-        let validation_guild_channel = guild_channels.get_mut(&validation_channel).ok_or_else(|| {
+        let mut validation_guild_channel = validation_channel.to_channel(&ctx.http).await?.guild().ok_or_else(|| {
             Error::from(format!("channel {} is set as the validation channel for user {}, but it was not found in the server.", validation_channel, user_id))
         })?;
+        let is_thread = validation_guild_channel.kind == ChannelType::PrivateThread;
 
         // Rename channel to indicate it is archived
         let channel_name = format!("📦-{}", validation_guild_channel.name);
 
-        validation_guild_channel.edit(&ctx, |channel| {
-            channel.name(&channel_name)
-        }).await?;
+        if is_thread {
+            validation_guild_channel.edit_thread(&ctx.http, |thread| {
+                thread.name(&channel_name)
+            }).await?;
+        } else {
+            validation_guild_channel.edit(&ctx, |channel| {
+                channel.name(&channel_name)
+            }).await?;
+        }
 
         info!("archived validation channel {} in guild {}", validation_channel, guild_id);
 
@@ -170,6 +390,15 @@ async fn onboarding_archive(ctx: &serenity::client::Context, bot: &Bot, interact
                 })
             })
         }).await?;
+
+        // Threads are archived last, as messages can no longer be edited once they are
+        if is_thread {
+            validation_channel.edit_thread(&ctx.http, |thread| {
+                thread.archived(true)
+            }).await?;
+        }
+
+        release_application_slot(ctx, &mut database, &guild_id, &user_id).await?;
     }
 
     Ok(())
@@ -180,6 +409,7 @@ async fn onboarding_delete(ctx: &serenity::client::Context, bot: &Bot, interacti
     let validation_user_to_channel_key = "validation_user_to_channel";
     let validation_channel_to_user_key = "validation_channel_to_user";
     let mut database = bot.database.lock().await;
+    let mut detached_user = None;
 
     if database.hexists(validation_channel_to_user_key, interaction.channel_id.as_u64())? {
         let user_id: u64 = database.hget(validation_channel_to_user_key, interaction.channel_id.as_u64())?;
@@ -192,11 +422,55 @@ async fn onboarding_delete(ctx: &serenity::client::Context, bot: &Bot, interacti
             database.hdel(&validation_key, "channel")?;
             database.hdel(&validation_user_to_channel_key, user_id.as_u64())?;
             database.hdel(&validation_channel_to_user_key, validation_channel.as_u64())?;
+
+            detached_user = Some(user_id);
         }
     }
 
     interaction.channel_id.delete(&ctx.http).await?;
 
+    if let Some(user_id) = detached_user {
+        release_application_slot(ctx, &mut database, &guild_id, &user_id).await?;
+    }
+
+    Ok(())
+}
+
+async fn onboarding_member_addition(ctx: &serenity::client::Context, bot: &Bot, member: &Member) -> Result<(), Error> {
+    let serves_guild = bot.serves_guild(member.guild_id).await?;
+
+    if !serves_guild {
+        return Ok(());
+    }
+
+    let mut database = bot.database.lock().await;
+
+    match used_invite(&ctx.http, &mut database, &member.guild_id).await {
+        Ok(Some(invite)) => {
+            let _: () = database.hset(format!("validation:{}:{}", member.guild_id, member.user.id), "invite", &invite)?;
+            let _: () = database.hincr(format!("invite_joins:{}", member.guild_id), &invite, 1)?;
+        },
+        Ok(None) => debug!("could not determine which invite member {} used to join guild {}", member.user.id, member.guild_id),
+        Err(error) => warn!("could not track the invite used by member {} to join guild {}: {}", member.user.id, member.guild_id, error),
+    }
+
+    let newcomer_role: Option<u64> = database.hget(format!("onboarding:{}", member.guild_id), "newcomer_role")?;
+
+    if let Some(newcomer_role) = newcomer_role.map(RoleId) {
+        match member.clone().add_role(&ctx.http, newcomer_role).await {
+            Ok(()) => database.sadd(format!("newcomers:{}", member.guild_id), member.user.id.as_u64())?,
+            Err(error) => error!("could not give the newcomer role to member {} of guild {}: {}", member.user.id, member.guild_id, error),
+        }
+    }
+
+    if onboarding_locked_down(&mut database, &member.guild_id)? {
+        return Ok(());
+    }
+
+    if let Some(reason) = record_raid_activity(&mut database, &member.guild_id, RaidActivity::Join, &member.user.id)? {
+        engage_lockdown(ctx, &mut database, &member.guild_id, &reason).await?;
+    }
+
     Ok(())
 }
 
@@ -206,6 +480,10 @@ async fn onboarding_member_removal(ctx: &serenity::client::Context, bot: &Bot, g
     let mut database = bot.database.lock().await;
     let validation_channel = pending_validation(&mut database, guild_id, &user.id).await?;
 
+    let _: () = database.lrem(format!("waitlist:{}", guild_id), 0, user.id.as_u64())?;
+    let _: () = database.srem(format!("newcomers:{}", guild_id), user.id.as_u64())?;
+    let _: () = database.zrem(SCHEDULED_INTRODUCTION_REMINDERS_KEY, format!("{}:{}", guild_id, user.id))?;
+
     if let Some(validation_channel) = validation_channel {
         validation_channel.send_message(&ctx.http, |message| {
             message
@@ -213,7 +491,7 @@ async fn onboarding_member_removal(ctx: &serenity::client::Context, bot: &Bot, g
                     embed
                         .colour(Colour::DARK_RED)
                         .title("Member left")
-                        .description(format!("{} ({}#{}) has left the server", user, user.name, user.discriminator))
+                        .description(format!("{} ({}) has left the server", user, utils::tag(&user.name, user.discriminator)))
                 })
                 .components(|components| {
                     components.create_action_row(|row| {
@@ -239,6 +517,43 @@ async fn onboarding_member_removal(ctx: &serenity::client::Context, bot: &Bot, g
 }
 
 async fn onboarding_start(ctx: &serenity::client::Context, bot: &Bot, interaction: &MessageComponentInteraction) -> Result<(), Error> {
+    let rules = {
+        let mut database = bot.database.lock().await;
+
+        pending_rules_acceptance(&mut database, &interaction.guild_id.unwrap(), &interaction.user.id).await?
+    };
+
+    if let Some((rules, rules_version)) = rules {
+        interaction.create_interaction_response(&ctx.http, |response| {
+            response
+                .kind(InteractionResponseType::ChannelMessageWithSource)
+                .interaction_response_data(|data| {
+                    data
+                        .ephemeral(true)
+                        .embed(|embed| {
+                            embed
+                                .colour(Colour::BLITZ_BLUE)
+                                .title("Server rules")
+                                .description(rules)
+                                .footer(|footer| footer.text(format!("Rules version {}", rules_version)))
+                        })
+                        .components(|components| {
+                            components.create_action_row(|row| {
+                                row.create_button(|button| {
+                                    button
+                                        .custom_id(identifiers::ONBOARDING_ACCEPT_RULES)
+                                        .style(ButtonStyle::Success)
+                                        .emoji('✅')
+                                        .label("I am 18+ and accept the rules")
+                                })
+                            })
+                        })
+                })
+        }).await?;
+
+        return Ok(());
+    }
+
     interaction.create_interaction_response(&ctx.http, |response| {
         response
             .kind(InteractionResponseType::DeferredChannelMessageWithSource)
@@ -252,7 +567,50 @@ async fn onboarding_start(ctx: &serenity::client::Context, bot: &Bot, interactio
     let validation_channel = pending_validation(&mut database, &guild_id, &member.user.id).await?;
 
     if validation_channel.is_none() {
-        setup_member_verification(ctx, &mut database, member).await?;
+        if let Some(pause) = onboarding_pause(&mut database, &guild_id)? {
+            interaction.create_followup_message(&ctx.http, |message| {
+                message.content(pause.notice())
+            }).await?;
+
+            return Ok(());
+        }
+
+        let mut locked_down = onboarding_locked_down(&mut database, &guild_id)?;
+
+        if !locked_down {
+            if let Some(reason) = record_raid_activity(&mut database, &guild_id, RaidActivity::Application, &member.user.id)? {
+                engage_lockdown(ctx, &mut database, &guild_id, &reason).await?;
+
+                locked_down = true;
+            }
+        }
+
+        if locked_down {
+            interaction.create_followup_message(&ctx.http, |message| {
+                message.content("Applications to join the server are temporarily paused. Please try again a bit later!")
+            }).await?;
+
+            return Ok(());
+        }
+
+        if let Some(position) = waitlist_position(&mut database, &guild_id, &member.user.id)? {
+            interaction.create_followup_message(&ctx.http, |message| {
+                message.content(format!(
+                    "Staff are currently busy with other applications, so you've been placed in the waitlist, at position {}. I'll open your validation channel and send you a DM as soon as it's your turn!",
+                    position,
+                ))
+            }).await?;
+
+            return Ok(());
+        }
+
+        if let Err(error) = setup_member_verification(ctx, &mut database, member).await {
+            interaction.create_followup_message(&ctx.http, |message| {
+                message.content("Sorry, something went wrong while processing your request to join the server. Please try again later, or reach out to a staff member.")
+            }).await?;
+
+            return Err(error);
+        }
     }
 
     interaction.create_followup_message(&ctx.http, |message| {
@@ -262,8 +620,474 @@ async fn onboarding_start(ctx: &serenity::client::Context, bot: &Bot, interactio
     Ok(())
 }
 
+async fn introduction_post(ctx: &serenity::client::Context, bot: &Bot, guild_id: &GuildId, message: &Message) -> Result<(), Error> {
+    let onboarding_key = format!("onboarding:{}", guild_id);
+    let introductions_key = format!("introductions:{}", guild_id);
+
+    // The database is only locked to read the settings, messages and threads are handled without it
+    let (threads, one_post, previous_introduction) = {
+        let mut database = bot.database.lock().await;
+        let threads: Option<bool> = database.hget(&onboarding_key, "introductions_threads")?;
+        let one_post: Option<bool> = database.hget(&onboarding_key, "introductions_one_post")?;
+        let previous_introduction: Option<u64> = database.hget(&introductions_key, message.author.id.as_u64())?;
+
+        (threads, one_post, previous_introduction)
+    };
+
+    if let (Some(true), Some(previous_introduction)) = (one_post, previous_introduction) {
+        match message.channel_id.message(&ctx.http, previous_introduction).await {
+            Ok(previous_introduction) => {
+                message.delete(&ctx.http).await?;
+
+                let direct_message = message.author.direct_message(&ctx.http, |direct_message| {
+                    direct_message.content(format!(
+                        "👋 You already introduced yourself in {}, so I removed your new post. You can edit your original introduction instead: {}",
+                        message.channel_id.mention(),
+                        previous_introduction.link(),
+                    ))
+                }).await;
+
+                if let Err(error) = direct_message {
+                    debug!("could not point member {} to their original introduction: {}", message.author.id, error);
+                }
+
+                return Ok(());
+            },
+            // The original introduction was deleted, so the new one replaces it
+            Err(SerenityError::Http(error)) if error.status_code() == Some(StatusCode::NOT_FOUND) => (),
+            Err(error) => return Err(error.into()),
+        }
+    }
+
+    {
+        let mut database = bot.database.lock().await;
+
+        let _: () = database.hset(&introductions_key, message.author.id.as_u64(), message.id.as_u64())?;
+        let _: () = database.zrem(SCHEDULED_INTRODUCTION_REMINDERS_KEY, format!("{}:{}", guild_id, message.author.id))?;
+    }
+
+    if threads.unwrap_or_default() {
+        let name: String = format!("Welcome, {}!", message.author.name).chars().take(THREAD_NAME_MAX_LENGTH).collect();
+
+        message.channel_id.create_public_thread(&ctx.http, message.id, |thread| thread.name(name)).await?;
+    }
+
+    Ok(())
+}
+
+// Role menu actions
+async fn role_menu_selection(ctx: &serenity::client::Context, bot: &Bot, interaction: &MessageComponentInteraction) -> Result<(), Error> {
+    let guild_id = interaction.guild_id.unwrap();
+    let mut member = interaction.member.clone().unwrap();
+    // Custom IDs are made of the menu name, followed by the role for buttons
+    let mut custom_id = interaction.data.custom_id.split(':').skip(1);
+    let name = custom_id.next().unwrap_or_default();
+    let button_role: Option<u64> = custom_id.next().and_then(|role| role.parse().ok());
+
+    let role_menu = {
+        let mut database = bot.database.lock().await;
+
+        utils::role_selection::<RoleMenu>(&mut database, guild_id, name)?
+    };
+
+    let role_menu = match role_menu {
+        Some(role_menu) => role_menu,
+        None => {
+            interaction.create_interaction_response(&ctx.http, |response| {
+                response
+                    .kind(InteractionResponseType::ChannelMessageWithSource)
+                    .interaction_response_data(|data| {
+                        data
+                            .content("This role menu no longer exists.")
+                            .ephemeral(true)
+                    })
+            }).await?;
+
+            return Ok(());
+        },
+    };
+
+    // Only toggle roles that are still offered, in case the menu changed since it was posted
+    let offered_roles = role_menu.roles.iter().map(|entry| entry.role).collect::<Vec<_>>();
+    let selected_roles = match button_role {
+        Some(role) => vec![RoleId(role)],
+        None => interaction.data.values.iter()
+            .filter_map(|role| role.parse().ok())
+            .map(RoleId)
+            .collect(),
+    };
+    let selected_roles = selected_roles.into_iter()
+        .filter(|role| offered_roles.contains(role))
+        .collect::<Vec<_>>();
+
+    let (added_roles, removed_roles) = match button_role {
+        // Buttons toggle a single role
+        Some(_) => selected_roles.into_iter().partition(|role| !member.roles.contains(role)),
+        // Select menus reflect the full set of roles a member picked in the menu
+        None => {
+            let added_roles = selected_roles.iter()
+                .filter(|role| !member.roles.contains(role))
+                .copied()
+                .collect::<Vec<_>>();
+            let removed_roles = offered_roles.iter()
+                .filter(|role| member.roles.contains(role) && !selected_roles.contains(role))
+                .copied()
+                .collect::<Vec<_>>();
+
+            (added_roles, removed_roles)
+        },
+    };
+
+    if !added_roles.is_empty() {
+        member.add_roles(&ctx.http, &added_roles).await?;
+    }
+
+    if !removed_roles.is_empty() {
+        member.remove_roles(&ctx.http, &removed_roles).await?;
+    }
+
+    debug!("member {} picked roles in role menu {} of guild {}: added {:?}, removed {:?}", member.user.id, role_menu.name, guild_id, added_roles, removed_roles);
+
+    let mentions = |roles: &[RoleId]| roles.iter().map(|role| role.mention().to_string()).collect::<Vec<_>>().join(", ");
+    let mut changes = Vec::new();
+
+    if !added_roles.is_empty() {
+        changes.push(format!("✅ Added {}", mentions(&added_roles)));
+    }
+
+    if !removed_roles.is_empty() {
+        changes.push(format!("❎ Removed {}", mentions(&removed_roles)));
+    }
+
+    if changes.is_empty() {
+        changes.push("Your roles are unchanged.".to_owned());
+    }
+
+    interaction.create_interaction_response(&ctx.http, |response| {
+        response
+            .kind(InteractionResponseType::ChannelMessageWithSource)
+            .interaction_response_data(|data| {
+                data
+                    .content(changes.join("\n"))
+                    .ephemeral(true)
+            })
+    }).await?;
+
+    Ok(())
+}
+
+async fn role_group_selection(ctx: &serenity::client::Context, bot: &Bot, interaction: &MessageComponentInteraction) -> Result<(), Error> {
+    let guild_id = interaction.guild_id.unwrap();
+    let mut member = interaction.member.clone().unwrap();
+    // Custom IDs are made of the group name, followed by the role
+    let mut custom_id = interaction.data.custom_id.split(':').skip(1);
+    let name = custom_id.next().unwrap_or_default();
+    let role: Option<u64> = custom_id.next().and_then(|role| role.parse().ok());
+
+    let (role_group, validated_role) = {
+        let mut database = bot.database.lock().await;
+        let validated_role: u64 = database.hget(format!("guild:{}", guild_id), "validated_role")?;
+
+        (utils::role_selection::<RoleGroup>(&mut database, guild_id, name)?, RoleId(validated_role))
+    };
+
+    // Only toggle roles that are still offered, in case the group changed since it was posted
+    let (role_group, role) = match (role_group, role.map(RoleId)) {
+        (Some(role_group), Some(role)) if role_group.roles.iter().any(|entry| entry.role == role) => (role_group, role),
+        _ => {
+            interaction.create_interaction_response(&ctx.http, |response| {
+                response
+                    .kind(InteractionResponseType::ChannelMessageWithSource)
+                    .interaction_response_data(|data| {
+                        data
+                            .content("This role is no longer offered.")
+                            .ephemeral(true)
+                    })
+            }).await?;
+
+            return Ok(());
+        },
+    };
+
+    let held_roles = role_group.roles.iter()
+        .map(|entry| entry.role)
+        .filter(|group_role| member.roles.contains(group_role))
+        .collect::<Vec<_>>();
+
+    let content = if held_roles.contains(&role) {
+        member.remove_role(&ctx.http, role).await?;
+
+        format!("❎ Removed {}", role.mention())
+    } else if role_group.validated_only && !member.roles.contains(&validated_role) {
+        format!("Only validated members can pick roles from **{}**.", role_group.title)
+    } else if role_group.single_choice {
+        if !held_roles.is_empty() {
+            member.remove_roles(&ctx.http, &held_roles).await?;
+        }
+
+        member.add_role(&ctx.http, role).await?;
+
+        format!("✅ Added {}", role.mention())
+    } else if role_group.max_roles.filter(|max_roles| held_roles.len() as u64 >= *max_roles).is_some() {
+        format!(
+            "You can pick at most {} roles from **{}**, remove one of yours first.",
+            role_group.max_roles.unwrap_or_default(),
+            role_group.title,
+        )
+    } else {
+        member.add_role(&ctx.http, role).await?;
+
+        format!("✅ Added {}", role.mention())
+    };
+
+    debug!("member {} picked role {} in role group {} of guild {}: {}", member.user.id, role, role_group.name, guild_id, content);
+
+    interaction.create_interaction_response(&ctx.http, |response| {
+        response
+            .kind(InteractionResponseType::ChannelMessageWithSource)
+            .interaction_response_data(|data| {
+                data
+                    .content(content)
+                    .ephemeral(true)
+            })
+    }).await?;
+
+    Ok(())
+}
+
+async fn guide_navigation(ctx: &serenity::client::Context, bot: &Bot, interaction: &MessageComponentInteraction) -> Result<(), Error> {
+    // Custom IDs are made of the guild, followed by the page to show
+    let mut custom_id = interaction.data.custom_id.split(':').skip(1);
+    let guild_id: Option<u64> = custom_id.next().and_then(|guild_id| guild_id.parse().ok());
+    let page: usize = custom_id.next().and_then(|page| page.parse().ok()).unwrap_or_default();
+
+    let guild_id = match guild_id {
+        Some(guild_id) if bot.serves_guild(GuildId(guild_id)).await? => GuildId(guild_id),
+        _ => return Ok(()),
+    };
+
+    let pages = {
+        let mut database = bot.database.lock().await;
+
+        utils::guide_pages(&mut database, guild_id)?
+    };
+
+    // The guide may have lost pages since it was sent
+    let page = page.min(pages.len().saturating_sub(1));
+    let guild_name = guild_id.name(&ctx.cache).unwrap_or_else(|| "the server".to_owned());
+
+    interaction.create_interaction_response(&ctx.http, |response| {
+        response
+            .kind(InteractionResponseType::UpdateMessage)
+            .interaction_response_data(|data| {
+                data
+                    .embed(|embed| utils::guide_embed(&guild_name, &pages, page, embed))
+                    .components(|components| utils::guide_components(guild_id, page, pages.len(), components))
+            })
+    }).await?;
+
+    Ok(())
+}
+
 // Utility functions
-async fn pending_validation<'a>(database: &mut MutexGuard<'a, Connection>, guild_id: &GuildId, user_id: &UserId) -> Result<Option<ChannelId>, Error> {
+/// Schedule a reminder for an approved member to introduce themselves, if the guild wants one
+/// and the member didn't already post an introduction.
+pub fn schedule_introduction_reminder(database: &mut Connection, guild_id: &GuildId, user_id: &UserId) -> Result<(), Error> {
+    let remind_days: Option<i64> = database.hget(format!("onboarding:{}", guild_id), "introductions_remind_days")?;
+    let remind_days = match remind_days.filter(|remind_days| *remind_days > 0) {
+        Some(remind_days) => remind_days,
+        None => return Ok(()),
+    };
+
+    if database.hexists(format!("introductions:{}", guild_id), user_id.as_u64())? {
+        return Ok(());
+    }
+
+    let remind_at = Timestamp::now().unix_timestamp() + remind_days * 86400;
+
+    let _: () = database.zadd(SCHEDULED_INTRODUCTION_REMINDERS_KEY, format!("{}:{}", guild_id, user_id), remind_at)?;
+
+    Ok(())
+}
+
+/// Cache the use counts of a guild's invites, so that the invite new members used can be found.
+pub async fn cache_invites<'a>(http: &Http, database: &mut MutexGuard<'a, Connection>, guild_id: &GuildId) -> Result<(), Error> {
+    let invites = guild_id.invites(http).await?;
+    let invites_key = format!("invites:{}", guild_id);
+    let invite_inviters_key = format!("invite_inviters:{}", guild_id);
+
+    let _: () = database.del(&invites_key)?;
+
+    for invite in invites {
+        let _: () = database.hset(&invites_key, &invite.code, invite.uses)?;
+
+        if let Some(inviter) = invite.inviter {
+            let _: () = database.hset(&invite_inviters_key, &invite.code, inviter.id.as_u64())?;
+        }
+    }
+
+    Ok(())
+}
+
+/// Find out which invite a member who just joined used, by comparing invite use counts with
+/// the cached ones.
+async fn used_invite<'a>(http: &Http, database: &mut MutexGuard<'a, Connection>, guild_id: &GuildId) -> Result<Option<String>, Error> {
+    let cached_invites: HashMap<String, u64> = database.hgetall(format!("invites:{}", guild_id))?;
+    let invites = guild_id.invites(http).await?;
+
+    // Invites that reached their maximum number of uses are deleted instead of being updated
+    let used_invite = invites.iter()
+        .find(|invite| invite.uses > cached_invites.get(&invite.code).copied().unwrap_or_default())
+        .map(|invite| invite.code.clone())
+        .or_else(|| {
+            cached_invites.keys()
+                .find(|code| !invites.iter().any(|invite| &&invite.code == code))
+                .cloned()
+        });
+
+    cache_invites(http, database, guild_id).await?;
+
+    Ok(used_invite)
+}
+
+/// Get the current closure of applications in a guild, if any.
+pub fn onboarding_pause(database: &mut Connection, guild_id: &GuildId) -> Result<Option<OnboardingPause>, Error> {
+    let onboarding_key = format!("onboarding:{}", guild_id);
+    let paused: Option<bool> = database.hget(&onboarding_key, "paused")?;
+
+    if !paused.unwrap_or_default() {
+        return Ok(None);
+    }
+
+    let until: Option<i64> = database.hget(&onboarding_key, "paused_until")?;
+
+    // The scheduler may not have caught up with an expired closure yet
+    if let Some(until) = until {
+        if until <= Timestamp::now().unix_timestamp() {
+            return Ok(None);
+        }
+    }
+
+    Ok(Some(OnboardingPause {
+        until,
+        message: database.hget(&onboarding_key, "pause_message")?,
+    }))
+}
+
+/// Close applications in a guild, optionally until a given time.
+pub async fn pause_onboarding<'a>(http: &Http, database: &mut MutexGuard<'a, Connection>, guild_id: &GuildId, pause: &OnboardingPause) -> Result<(), Error> {
+    let onboarding_key = format!("onboarding:{}", guild_id);
+
+    let _: () = database.hset(&onboarding_key, "paused", true)?;
+
+    match pause.until {
+        Some(until) => {
+            let _: () = database.hset(&onboarding_key, "paused_until", until)?;
+            let _: () = database.zadd(SCHEDULED_RESUMES_KEY, guild_id.as_u64(), until)?;
+        },
+        None => {
+            let _: () = database.hdel(&onboarding_key, "paused_until")?;
+            let _: () = database.zrem(SCHEDULED_RESUMES_KEY, guild_id.as_u64())?;
+        },
+    }
+
+    match &pause.message {
+        Some(message) => database.hset(&onboarding_key, "pause_message", message)?,
+        None => database.hdel(&onboarding_key, "pause_message")?,
+    }
+
+    info!("paused onboarding in guild {}", guild_id);
+
+    messages::publish(http, database, guild_id, &PersistentMessage::Welcome).await
+}
+
+/// Reopen applications in a guild.
+pub async fn resume_onboarding<'a>(ctx: &serenity::client::Context, database: &mut MutexGuard<'a, Connection>, guild_id: &GuildId) -> Result<(), Error> {
+    let onboarding_key = format!("onboarding:{}", guild_id);
+
+    let _: () = database.hdel(&onboarding_key, &["paused", "paused_until", "pause_message"])?;
+    let _: () = database.zrem(SCHEDULED_RESUMES_KEY, guild_id.as_u64())?;
+
+    info!("resumed onboarding in guild {}", guild_id);
+
+    messages::publish(&ctx.http, database, guild_id, &PersistentMessage::Welcome).await?;
+    process_waitlist(ctx, database, guild_id).await
+}
+
+async fn engage_lockdown<'a>(ctx: &serenity::client::Context, database: &mut MutexGuard<'a, Connection>, guild_id: &GuildId, reason: &str) -> Result<(), Error> {
+    let onboarding_key = format!("onboarding:{}", guild_id);
+    let notify_role: Option<u64> = database.hget(&onboarding_key, "notify_role")?;
+    let notify_role = notify_role.map(|role| RoleId(role).mention().to_string()).unwrap_or_default();
+
+    let _: () = database.hset(&onboarding_key, "lockdown", true)?;
+
+    info!("engaged onboarding lockdown in guild {}: {}", guild_id, reason);
+
+    utils::log_to_staff(&ctx.http, database, *guild_id, format!(
+        "🚨 {} Onboarding lockdown engaged: {}. New applications are paused until a staff member runs `/onboarding lockdown off`.",
+        notify_role,
+        reason,
+    )).await?;
+
+    Ok(())
+}
+
+fn onboarding_locked_down(database: &mut Connection, guild_id: &GuildId) -> Result<bool, Error> {
+    let lockdown: Option<bool> = database.hget(format!("onboarding:{}", guild_id), "lockdown")?;
+
+    Ok(lockdown.unwrap_or_default())
+}
+
+/// Record a join or an application, and return why the guild should be locked down if the
+/// configured rate limit for this kind of activity is exceeded. Nothing is recorded until raid
+/// protection is configured with /onboarding raid_protection.
+fn record_raid_activity(database: &mut Connection, guild_id: &GuildId, activity: RaidActivity, user_id: &UserId) -> Result<Option<String>, Error> {
+    let onboarding_key = format!("onboarding:{}", guild_id);
+    let activity_key = format!("raid:{}:{}", guild_id, activity.name());
+    let threshold: Option<u64> = database.hget(&onboarding_key, activity.threshold_field())?;
+    let threshold = match threshold.filter(|threshold| *threshold > 0) {
+        Some(threshold) => threshold,
+        None => return Ok(None),
+    };
+    let window: Option<i64> = database.hget(&onboarding_key, "raid_window")?;
+    let window = window.unwrap_or(DEFAULT_RAID_WINDOW);
+    let now = Timestamp::now().unix_timestamp();
+
+    let _: () = database.zadd(&activity_key, user_id.as_u64(), now)?;
+    let _: () = database.zrembyscore(&activity_key, "-inf", now - window)?;
+    let _: () = database.expire(&activity_key, window as usize)?;
+
+    let count: u64 = database.zcard(&activity_key)?;
+
+    if count <= threshold {
+        return Ok(None);
+    }
+
+    Ok(Some(format!("{} {}s in the last {} seconds", count, activity.name(), window)))
+}
+
+async fn pending_rules_acceptance<'a>(database: &mut MutexGuard<'a, Connection>, guild_id: &GuildId, user_id: &UserId) -> Result<Option<(String, String)>, Error> {
+    let onboarding_key = format!("onboarding:{}", guild_id);
+    let validation_key = format!("validation:{}:{}", guild_id, user_id);
+    let rules_required: Option<bool> = database.hget(&onboarding_key, "rules_required")?;
+
+    if !rules_required.unwrap_or_default() {
+        return Ok(None);
+    }
+
+    let rules: String = database.hget(&onboarding_key, "rules")?;
+    let rules_version: Option<String> = database.hget(&onboarding_key, "rules_version")?;
+    let rules_version = rules_version.unwrap_or_else(|| "1".into());
+    let accepted_rules_version: Option<String> = database.hget(&validation_key, "rules_version")?;
+
+    if accepted_rules_version.as_ref() == Some(&rules_version) {
+        return Ok(None);
+    }
+
+    Ok(Some((rules, rules_version)))
+}
+
+pub async fn pending_validation<'a>(database: &mut MutexGuard<'a, Connection>, guild_id: &GuildId, user_id: &UserId) -> Result<Option<ChannelId>, Error> {
     let user_id = user_id.as_u64();
     let validation_key = format!("validation:{}:{}", guild_id, user_id);
 
@@ -274,7 +1098,157 @@ async fn pending_validation<'a>(database: &mut MutexGuard<'a, Connection>, guild
     Ok(Some(ChannelId(database.hget(&validation_key, "channel")?)))
 }
 
-async fn setup_member_verification<'a>(ctx: &serenity::client::Context, database: &mut MutexGuard<'a, Connection>, member: &Member) -> Result<(), Error> {
+/// Place a member in the waitlist if the guild's cap on open applications is reached, and
+/// return their position in it.
+fn waitlist_position(database: &mut Connection, guild_id: &GuildId, user_id: &UserId) -> Result<Option<usize>, Error> {
+    let waitlist_key = format!("waitlist:{}", guild_id);
+    let waitlist: Vec<u64> = database.lrange(&waitlist_key, 0, -1)?;
+
+    if let Some(position) = waitlist.iter().position(|waiting_user| waiting_user == user_id.as_u64()) {
+        return Ok(Some(position + 1));
+    }
+
+    let max_open_applications: Option<u64> = database.hget(format!("onboarding:{}", guild_id), "max_open_applications")?;
+
+    // Members already waiting go first, even if a slot freed up in the meantime
+    if waitlist.is_empty() {
+        let max_open_applications = match max_open_applications {
+            Some(max_open_applications) if max_open_applications > 0 => max_open_applications,
+            _ => return Ok(None),
+        };
+        let open_applications: u64 = database.scard(format!("open_validations:{}", guild_id))?;
+
+        if open_applications < max_open_applications {
+            return Ok(None);
+        }
+    }
+
+    let position: usize = database.rpush(&waitlist_key, user_id.as_u64())?;
+
+    info!("placed member {} in the waitlist of guild {} at position {}", user_id, guild_id, position);
+
+    Ok(Some(position))
+}
+
+/// Mark a member's application as closed, and open validation channels for members in the
+/// waitlist if this freed up a slot.
+pub async fn release_application_slot<'a>(ctx: &serenity::client::Context, database: &mut MutexGuard<'a, Connection>, guild_id: &GuildId, user_id: &UserId) -> Result<(), Error> {
+    let _: () = database.srem(format!("open_validations:{}", guild_id), user_id.as_u64())?;
+
+    process_waitlist(ctx, database, guild_id).await
+}
+
+/// Open validation channels for members in the waitlist, as long as there are free slots.
+pub async fn process_waitlist<'a>(ctx: &serenity::client::Context, database: &mut MutexGuard<'a, Connection>, guild_id: &GuildId) -> Result<(), Error> {
+    if onboarding_pause(database, guild_id)?.is_some() || onboarding_locked_down(database, guild_id)? {
+        return Ok(());
+    }
+
+    let waitlist_key = format!("waitlist:{}", guild_id);
+    let max_open_applications: Option<u64> = database.hget(format!("onboarding:{}", guild_id), "max_open_applications")?;
+    let max_open_applications = max_open_applications.filter(|max_open_applications| *max_open_applications > 0);
+
+    // Members whose validation channel couldn't be opened go back to the front of the waitlist
+    let mut failed_members = Vec::new();
+
+    loop {
+        if let Some(max_open_applications) = max_open_applications {
+            let open_applications: u64 = database.scard(format!("open_validations:{}", guild_id))?;
+
+            if open_applications >= max_open_applications {
+                break;
+            }
+        }
+
+        let user_id: Option<u64> = database.lpop(&waitlist_key, None)?;
+        let user_id = match user_id {
+            Some(user_id) => UserId(user_id),
+            None => break,
+        };
+
+        // Members who left while waiting are simply skipped
+        let member = match guild_id.member(&ctx.http, user_id).await {
+            Ok(member) => member,
+            Err(error) => {
+                debug!("skipping member {} of the waitlist of guild {}: {}", user_id, guild_id, error);
+
+                continue;
+            },
+        };
+
+        if let Err(error) = setup_member_verification(ctx, database, &member).await {
+            error!("could not open the validation channel of member {} of the waitlist of guild {}: {}", user_id, guild_id, error);
+
+            failed_members.push(user_id);
+
+            let logged = utils::log_to_staff(&ctx.http, database, *guild_id, format!(
+                "⚠️ Could not open the validation channel of {} from the waitlist, they were put back in line: {}",
+                user_id.mention(),
+                error,
+            )).await;
+
+            if let Err(error) = logged {
+                warn!("could not log a waitlist failure to the staff of guild {}: {}", guild_id, error);
+            }
+
+            continue;
+        }
+
+        if let Some(validation_channel) = pending_validation(database, guild_id, &user_id).await? {
+            let direct_message = member.user.direct_message(&ctx.http, |message| {
+                message.content(format!(
+                    "👋 It's your turn! Your request to join the server is now being discussed in {}.",
+                    validation_channel.mention(),
+                ))
+            }).await;
+
+            if let Err(error) = direct_message {
+                debug!("could not notify member {} that their validation channel was opened: {}", user_id, error);
+            }
+        }
+    }
+
+    for user_id in failed_members.iter().rev() {
+        let _: () = database.lpush(&waitlist_key, user_id.as_u64())?;
+    }
+
+    Ok(())
+}
+
+/// Give a member back access to their validation channel or thread, e.g. when their validation is revoked.
+pub async fn restore_member_verification<'a>(ctx: &serenity::client::Context, database: &mut MutexGuard<'a, Connection>, member: &Member, validation_channel: ChannelId) -> Result<(), Error> {
+    match validation_channel.to_channel(&ctx.http).await? {
+        Channel::Guild(channel) if channel.kind == ChannelType::PrivateThread => {
+            validation_channel.add_thread_member(&ctx.http, member.user.id).await?;
+        },
+        _ => {
+            validation_channel.create_permission(&ctx.http, &PermissionOverwrite {
+                allow: applicant_permissions(database, &member.guild_id)?,
+                deny: Permissions::empty(),
+                kind: PermissionOverwriteType::Member(member.user.id),
+            }).await?;
+        },
+    }
+
+    // The restored validation takes an application slot again until it is approved or denied
+    let _: () = database.sadd(format!("open_validations:{}", member.guild_id), member.user.id.as_u64())?;
+
+    Ok(())
+}
+
+/// Open a validation channel or thread for a member applying to the guild, and count the application.
+pub async fn setup_member_verification<'a>(ctx: &serenity::client::Context, database: &mut MutexGuard<'a, Connection>, member: &Member) -> Result<(), Error> {
+    open_member_verification(ctx, database, member).await?;
+
+    utils::record_history(database, member.guild_id, member.user.id, &HistoryEntry::new(HistoryEvent::Application, None, None))?;
+    utils::record_invite_conversion(database, member.guild_id, member.user.id, InviteConversion::Application)?;
+
+    Ok(())
+}
+
+/// Open a validation channel or thread for a member, without counting it as an application, e.g.
+/// when their validation is revoked.
+pub async fn open_member_verification<'a>(ctx: &serenity::client::Context, database: &mut MutexGuard<'a, Connection>, member: &Member) -> Result<(), Error> {
     let guild_id = member.guild_id;
     let guild_key = format!("guild:{}", guild_id);
     let onboarding_key = format!("onboarding:{}", guild_id);
@@ -286,31 +1260,250 @@ async fn setup_member_verification<'a>(ctx: &serenity::client::Context, database
     let notify_role = roles.get(&RoleId(notify_role)).ok_or_else(|| {
         Error::from(format!("role {} is configured as the notify role for onboarding, but it doesn't exist in the guild", notify_role))
     })?;
-    let validation_category = database.hget(&guild_key, "validation_category")?;
-    let validation_category = ChannelId(validation_category);
-
-    let member_channel = guild_id.create_channel(&ctx.http, |channel| {
-        channel
-            .kind(ChannelType::Text)
-            .name(member.user.tag().replace('#', "-"))
-            .category(validation_category)
-    }).await?;
+    let channel_name_template: Option<String> = database.hget(&onboarding_key, "channel_name_template")?;
+    let channel_name_template = channel_name_template.unwrap_or_else(|| utils::DEFAULT_CHANNEL_NAME_TEMPLATE.into());
 
-    member_channel.create_permission(&ctx.http, &PermissionOverwrite {
-        allow: Permissions::VIEW_CHANNEL | Permissions::READ_MESSAGE_HISTORY | Permissions::SEND_MESSAGES,
-        deny: Permissions::empty(),
-        kind: PermissionOverwriteType::Member(member.user.id),
-    }).await?;
+    // Only bump the guild's validation counter if the channel name actually uses it
+    let validation_counter = if channel_name_template.contains("{n}") {
+        database.hincr(&onboarding_key, "validation_counter", 1)?
+    } else {
+        0
+    };
+    let channel_name = utils::validation_channel_name(&channel_name_template, validation_counter, &member.user);
+
+    let onboarding_mode: Option<String> = database.hget(&onboarding_key, "mode")?;
+    let onboarding_mode = onboarding_mode.and_then(|mode| mode.parse().ok()).unwrap_or_default();
+
+    let member_channel = match onboarding_mode {
+        OnboardingMode::Channel => {
+            let validation_category = database.hget(&guild_key, "validation_category")?;
+            let validation_category = ChannelId(validation_category);
+            let bot_user = ctx.http.get_current_user().await?;
+            let permission_overwrites = validation_channel_permissions(database, member, notify_role, bot_user.id).await?;
+
+            let member_channel = guild_id.create_channel(&ctx.http, |channel| {
+                channel
+                    .kind(ChannelType::Text)
+                    .name(&channel_name)
+                    .category(validation_category)
+                    .permissions(permission_overwrites)
+            }).await?;
+
+            // Double-check that the channel really is private before pinging the member in it,
+            // as the category or a misconfigured role could still expose it to everyone
+            if validation_channel_is_public(ctx, &roles, member_channel.id).await? {
+                member_channel.delete(&ctx.http).await?;
+
+                utils::log_to_staff(&ctx.http, database, guild_id, format!(
+                    "🚨 Aborted the creation of a validation channel for {}, as it would have been visible to everyone. Please check the permissions of the validation category and of the @everyone role.",
+                    member.mention(),
+                )).await?;
+
+                return Err(Error::from(format!("validation channel for member {} in guild {} was visible to everyone", member.user.id, guild_id)));
+            }
 
-    database.hset(&validation_key, "channel", member_channel.id.as_u64())?;
-    database.hset(validation_channel_to_user_key, member_channel.id.as_u64(), member.user.id.as_u64())?;
-    database.hset(validation_user_to_channel_key, member.user.id.as_u64(), member_channel.id.as_u64())?;
+            member_channel
+        },
+        OnboardingMode::Thread => {
+            let threads_channel = database.hget(&onboarding_key, "threads_channel")?;
+            let threads_channel = ChannelId(threads_channel);
+
+            // Staff members are added to the thread when the notify role is mentioned in the wait notice
+            let member_thread = threads_channel.create_private_thread(&ctx.http, |thread| {
+                thread
+                    .name(&channel_name)
+                    .auto_archive_duration(10080)
+            }).await?;
+
+            member_thread.id.add_thread_member(&ctx.http, member.user.id).await?;
+
+            member_thread
+        },
+    };
+
+    let _: () = database.hset(&validation_key, "channel", member_channel.id.as_u64())?;
+    let _: () = database.hset(validation_channel_to_user_key, member_channel.id.as_u64(), member.user.id.as_u64())?;
+    let _: () = database.hset(validation_user_to_channel_key, member.user.id.as_u64(), member_channel.id.as_u64())?;
+    let _: () = database.sadd(format!("open_validations:{}", guild_id), member.user.id.as_u64())?;
 
     member_channel.send_message(&ctx.http, |message| new_member_wait_notice(member, notify_role, message)).await?;
 
+    let applicant_summary = applicant_summary(database, member)?;
+    let notes = utils::member_notes(database, guild_id, member.user.id)?;
+
+    // The application is open whatever happens to the summary, so failing to share it is only logged
+    if let Err(error) = share_applicant_summary(ctx, database, member, notify_role, member_channel.id, onboarding_mode, &applicant_summary, &notes).await {
+        warn!("could not share the summary of member {} of guild {} with staff: {}", member.user.id, guild_id, error);
+    }
+
+    Ok(())
+}
+
+/// Share the summary of an applicant and the notes staff wrote about them where only staff can
+/// read them: the log channel, or else a private thread of their validation channel, or the
+/// channel validation threads are created in.
+#[allow(clippy::too_many_arguments)]
+async fn share_applicant_summary<'a>(ctx: &serenity::client::Context, database: &mut MutexGuard<'a, Connection>, member: &Member, notify_role: &Role, member_channel: ChannelId, onboarding_mode: OnboardingMode, summary: &ApplicantSummary, notes: &[Note]) -> Result<(), Error> {
+    let guild_id = member.guild_id;
+    let log_channel: Option<u64> = database.hget(format!("guild:{}", guild_id), "log_channel")?;
+    let (staff_channel, mention) = match (log_channel, onboarding_mode) {
+        (Some(log_channel), _) => (ChannelId(log_channel), String::new()),
+        (None, OnboardingMode::Channel) => {
+            // Staff members are added to the thread when the notify role is mentioned
+            let staff_thread = member_channel.create_private_thread(&ctx.http, |thread| {
+                thread
+                    .name("Staff")
+                    .kind(ChannelType::PrivateThread)
+                    .auto_archive_duration(10080)
+            }).await?;
+
+            (staff_thread.id, format!("{} ", notify_role.mention()))
+        },
+        (None, OnboardingMode::Thread) => (ChannelId(database.hget(format!("onboarding:{}", guild_id), "threads_channel")?), String::new()),
+    };
+
+    staff_channel.send_message(&ctx.http, |message| {
+        message
+            .content(format!(
+                "📋 {}{} opened a new application in {}",
+                mention,
+                member.mention(),
+                member_channel.mention(),
+            ))
+            .embed(|embed| applicant_summary_embed(member, summary, embed));
+
+        if !notes.is_empty() {
+            message.add_embed(|embed| utils::notes_embed(&member.user, notes, embed));
+        }
+
+        message
+    }).await?;
+
     Ok(())
 }
 
+fn applicant_summary(database: &mut Connection, member: &Member) -> Result<ApplicantSummary, Error> {
+    let onboarding_key = format!("onboarding:{}", member.guild_id);
+    let validation_key = format!("validation:{}:{}", member.guild_id, member.user.id);
+    let rules_version: Option<String> = database.hget(&validation_key, "rules_version")?;
+    let rules_accepted_at: Option<i64> = database.hget(&validation_key, "rules_accepted_at")?;
+    let history = utils::member_history(database, member.guild_id, member.user.id)?;
+    let previous_applications = history.iter().filter(|entry| entry.event == HistoryEvent::Application).count();
+    let denials = history.iter().filter(|entry| entry.event == HistoryEvent::Denial).count();
+    let notes = utils::member_notes(database, member.guild_id, member.user.id)?.len();
+    let vouches = database.scard(format!("vouches:{}:{}", member.guild_id, member.user.id))?;
+    let invite: Option<String> = database.hget(&validation_key, "invite")?;
+    let invite = match invite {
+        Some(code) => {
+            let label: Option<String> = database.hget(format!("invite_labels:{}", member.guild_id), &code)?;
+            let inviter: Option<u64> = database.hget(format!("invite_inviters:{}", member.guild_id), &code)?;
+            let mut invite = format!("`{}`", code);
+
+            if let Some(label) = label {
+                invite.push_str(&format!(" ({})", label));
+            }
+
+            if let Some(inviter) = inviter {
+                invite.push_str(&format!(", created by {}", UserId(inviter).mention()));
+            }
+
+            Some(invite)
+        },
+        None => None,
+    };
+    let min_account_age: Option<i64> = database.hget(&onboarding_key, "risk_min_account_age")?;
+    let flag_default_avatar: Option<bool> = database.hget(&onboarding_key, "risk_flag_default_avatar")?;
+    let mut flags = vec![];
+
+    if let Some(min_account_age) = min_account_age.filter(|days| *days > 0) {
+        let account_age = Timestamp::now().unix_timestamp() - member.user.created_at().unix_timestamp();
+
+        if account_age < min_account_age * 86400 {
+            flags.push(format!("Account is younger than {} days", min_account_age));
+        }
+    }
+
+    if flag_default_avatar.unwrap_or_default() && member.user.avatar.is_none() {
+        flags.push("Account has the default avatar".to_owned());
+    }
+
+    if denials > 0 {
+        flags.push(format!("Application was denied {} time(s) before", denials));
+    }
+
+    Ok(ApplicantSummary {
+        rules_acceptance: rules_version.zip(rules_accepted_at),
+        previous_applications,
+        denials,
+        notes,
+        vouches,
+        invite,
+        flags,
+    })
+}
+
+fn applicant_permissions(database: &mut Connection, guild_id: &GuildId) -> Result<Permissions, Error> {
+    let onboarding_key = format!("onboarding:{}", guild_id);
+    let attach_files: Option<bool> = database.hget(&onboarding_key, "applicant_attach_files")?;
+    let embed_links: Option<bool> = database.hget(&onboarding_key, "applicant_embed_links")?;
+    let mut applicant_permissions = Permissions::VIEW_CHANNEL | Permissions::READ_MESSAGE_HISTORY | Permissions::SEND_MESSAGES;
+
+    if attach_files.unwrap_or_default() {
+        applicant_permissions |= Permissions::ATTACH_FILES;
+    }
+
+    if embed_links.unwrap_or_default() {
+        applicant_permissions |= Permissions::EMBED_LINKS;
+    }
+
+    Ok(applicant_permissions)
+}
+
+async fn validation_channel_permissions<'a>(database: &mut MutexGuard<'a, Connection>, member: &Member, staff_role: &Role, bot_user_id: UserId) -> Result<Vec<PermissionOverwrite>, Error> {
+    let channel_access = Permissions::VIEW_CHANNEL | Permissions::READ_MESSAGE_HISTORY | Permissions::SEND_MESSAGES;
+    let applicant_permissions = applicant_permissions(database, &member.guild_id)?;
+
+    Ok(vec![
+        PermissionOverwrite {
+            allow: Permissions::empty(),
+            deny: Permissions::VIEW_CHANNEL,
+            kind: PermissionOverwriteType::Role(RoleId(member.guild_id.0)),
+        },
+        PermissionOverwrite {
+            allow: channel_access,
+            deny: Permissions::empty(),
+            kind: PermissionOverwriteType::Role(staff_role.id),
+        },
+        PermissionOverwrite {
+            allow: channel_access | Permissions::MANAGE_CHANNELS,
+            deny: Permissions::empty(),
+            kind: PermissionOverwriteType::Member(bot_user_id),
+        },
+        PermissionOverwrite {
+            allow: applicant_permissions,
+            deny: Permissions::empty(),
+            kind: PermissionOverwriteType::Member(member.user.id),
+        },
+    ])
+}
+
+async fn validation_channel_is_public(ctx: &serenity::client::Context, roles: &HashMap<RoleId, Role>, channel_id: ChannelId) -> Result<bool, Error> {
+    let channel = channel_id.to_channel(&ctx.http).await?.guild().ok_or_else(|| {
+        Error::from(format!("validation channel {} is not a guild channel", channel_id))
+    })?;
+    let everyone_role_id = RoleId(channel.guild_id.0);
+    let everyone_role = roles.get(&everyone_role_id).ok_or_else(|| {
+        Error::from(format!("@everyone role not found in guild {}", channel.guild_id))
+    })?;
+    let mut everyone_permissions = everyone_role.permissions;
+
+    if let Some(overwrite) = channel.permission_overwrites.iter().find(|overwrite| overwrite.kind == PermissionOverwriteType::Role(everyone_role_id)) {
+        everyone_permissions = (everyone_permissions & !overwrite.deny) | overwrite.allow;
+    }
+
+    Ok(everyone_permissions.administrator() || everyone_permissions.view_channel())
+}
+
 fn reply_to_join_request<'a, 'b>(pending_validation_channel: Option<ChannelId>, followup_message: &'b mut CreateInteractionResponseFollowup<'a>) -> &'b mut CreateInteractionResponseFollowup<'a> {
     match pending_validation_channel {
         Some(channel) => {
@@ -324,33 +1517,6 @@ fn reply_to_join_request<'a, 'b>(pending_validation_channel: Option<ChannelId>,
     }
 }
 
-fn welcome_instructions<'a, 'b>(message: &'b mut CreateMessage<'a>) -> &'b mut CreateMessage<'a> {
-    message
-        .embed(|embed| {
-            embed
-                .colour(Colour::BLITZ_BLUE)
-                .title("Welcome to Transpouce! 👋")
-                .description(
-                    "This server is a safe space for discussion and exchange among trans and/or questioning people who are living in the Netherlands. It is open to 18+ people only, and is not tied to any existing organization, association or group.
-
-The main language of the server is English.
-
-To keep our space safe and gezellig, we have a simple verification process for new members in place. By clicking the button below, you'll be added to a private channel with the server staff, where we'll ask you some questions and get to know each other a little! <:transkitty:1000713242236178442>"
-                )
-        })
-        .components(|components| {
-            components.create_action_row(|row| {
-                row.create_button(|button| {
-                    button
-                        .custom_id(identifiers::ONBOARDING_START)
-                        .style(ButtonStyle::Primary)
-                        .emoji('🚪')
-                        .label("Enter")
-                })
-            })
-        })
-}
-
 fn new_member_wait_notice<'a, 'b>(member: &Member, notify_role: &Role, message: &'b mut CreateMessage<'a>) -> &'b mut CreateMessage<'a> {
     message
         .content(format!("{} {}", member, notify_role))
@@ -366,3 +1532,35 @@ To speed up the validation process, can you already tell us a few words about yo
                 )
         })
 }
+
+fn applicant_summary_embed<'a>(member: &Member, summary: &ApplicantSummary, embed: &'a mut CreateEmbed) -> &'a mut CreateEmbed {
+    let joined_at = member.joined_at
+        .map(|joined_at| format!("<t:{}:R>", joined_at.unix_timestamp()))
+        .unwrap_or_else(|| "Unknown".into());
+
+    embed
+        .colour(if summary.flags.is_empty() { Colour::DARK_GREEN } else { Colour::ORANGE })
+        .title(format!("Applicant summary of {}", utils::member_tag(member)))
+        .field("Account created", format!("<t:{}:R>", member.user.created_at().unix_timestamp()), true)
+        .field("Joined server", joined_at, true)
+        .field("Avatar", if member.user.avatar.is_none() { "Default" } else { "Custom" }, true)
+        .field("Previous applications", summary.previous_applications, true)
+        .field("Denials", summary.denials, true)
+        .field("Staff notes", summary.notes, true)
+        .field("Vouches", summary.vouches, true)
+        .field("Invite", summary.invite.as_deref().unwrap_or("Unknown"), false);
+
+    if !summary.flags.is_empty() {
+        embed.field("Flags", summary.flags.iter().map(|flag| format!("⚠️ {}", flag)).collect::<Vec<_>>().join("\n"), false);
+    }
+
+    if let Some((rules_version, rules_accepted_at)) = &summary.rules_acceptance {
+        embed.footer(|footer| footer.text(format!("Confirmed being 18+ and accepted the rules (version {})", rules_version)));
+
+        if let Ok(rules_accepted_at) = Timestamp::from_unix_timestamp(*rules_accepted_at) {
+            embed.timestamp(rules_accepted_at);
+        }
+    }
+
+    embed
+}
@@ -1,4 +1,15 @@
 // Custom IDs for onboarding message components and interactions
+pub const ONBOARDING_ACCEPT_RULES: &str = "onboarding_accept_rules";
+pub const ONBOARDING_APPROVAL_ROLES: &str = "onboarding_approval_roles";
 pub const ONBOARDING_ARCHIVE: &str = "onboarding_archive";
 pub const ONBOARDING_DELETE: &str = "onboarding_delete";
 pub const ONBOARDING_START: &str = "onboarding_start";
+
+// Prefix of the custom IDs of role menu components, followed by the menu name
+pub const ROLE_MENU: &str = "role_menu";
+
+// Prefix of the custom IDs of role group buttons, followed by the group name and the role
+pub const ROLE_GROUP: &str = "role_group";
+
+// Prefix of the custom IDs of guide navigation buttons, followed by the guild and the page to show
+pub const GUIDE: &str = "guide";
@@ -1,10 +1,15 @@
 mod commands;
 mod events;
 mod identifiers;
+mod messages;
 mod models;
+mod tasks;
+mod utils;
 
+use std::collections::HashMap;
 use std::env;
 use std::error;
+use std::sync::Arc;
 
 use config::{Config, Environment, File};
 use derivative::Derivative;
@@ -13,8 +18,8 @@ use poise::builtins;
 use redis::{Commands, RedisResult};
 use serenity::model::application::command::Command;
 use serenity::model::gateway::GatewayIntents;
-use serenity::model::id::GuildId;
-use serenity::prelude::Mutex;
+use serenity::model::id::{ChannelId, GuildId};
+use serenity::prelude::{Mutex, RwLock};
 use tracing::{debug, error, info};
 
 type Error = Box<dyn error::Error + Send + Sync>;
@@ -24,7 +29,10 @@ type Context<'a> = poise::Context<'a, Bot, Error>;
 #[derivative(Debug)]
 pub struct Bot {
     #[derivative(Debug="ignore")]
-    database: Mutex<redis::Connection>,
+    database: Arc<Mutex<redis::Connection>>,
+    /// Introductions channel of each guild, cached as every message posted in a guild is checked
+    /// against it.
+    introductions_channels: RwLock<HashMap<GuildId, Option<ChannelId>>>,
     run_mode: String,
 }
 
@@ -34,6 +42,27 @@ impl Bot {
 
         database.hget(format!("guild:{}", guild_id), "configured")
     }
+
+    async fn introductions_channel(&self, guild_id: GuildId) -> RedisResult<Option<ChannelId>> {
+        if let Some(introductions_channel) = self.introductions_channels.read().await.get(&guild_id) {
+            return Ok(*introductions_channel);
+        }
+
+        let introductions_channel: Option<u64> = {
+            let mut database = self.database.lock().await;
+
+            database.hget(format!("guild:{}", guild_id), "introductions_channel")?
+        };
+        let introductions_channel = introductions_channel.map(ChannelId);
+
+        self.introductions_channels.write().await.insert(guild_id, introductions_channel);
+
+        Ok(introductions_channel)
+    }
+
+    async fn set_introductions_channel(&self, guild_id: GuildId, introductions_channel: ChannelId) {
+        self.introductions_channels.write().await.insert(guild_id, Some(introductions_channel));
+    }
 }
 
 async fn error_handler(error: FrameworkError<'_, Bot, Error>) {
@@ -94,20 +123,32 @@ async fn main() {
         redis::cmd("AUTH").arg(&auth_info).execute(&mut database);
     }
 
-    let database = Mutex::new(database);
+    let database = Arc::new(Mutex::new(database));
 
     // Create bot instance to be passed as context to command functions
-    let bot = Bot { database, run_mode };
+    let bot = Bot { database, introductions_channels: RwLock::new(HashMap::new()), run_mode };
 
     // Connect to Discord and run bot framework
     let bot_token = configuration.get_string("discord.bot.token").expect("missing or incorrect discord bot token");
-    let intents = GatewayIntents::GUILDS | GatewayIntents::GUILD_MEMBERS;
+    let intents = GatewayIntents::GUILDS | GatewayIntents::GUILD_MEMBERS | GatewayIntents::GUILD_INVITES | GatewayIntents::GUILD_MESSAGES;
     let framework = Framework::builder()
         .options(FrameworkOptions {
             commands: vec![
                 commands::ping(),
                 commands::setup(),
                 commands::onboarding(),
+                commands::note(),
+                commands::vouch(),
+                commands::invites(),
+                commands::rolemenu(),
+                commands::rolegroup(),
+                commands::panel(),
+                commands::temprole(),
+                commands::pronouns(),
+                commands::show_pronouns(),
+                commands::guide(),
+                commands::welcome(),
+                commands::config(),
             ],
             event_handler: |ctx, event, framework, user_data| {
                 Box::pin(events::listener(ctx, event, framework, user_data))
@@ -148,6 +189,8 @@ async fn main() {
                 Err(error) => error!("error registering slash commands: {}", error),
             }
 
+            tokio::spawn(tasks::scheduler(ctx.clone(), bot.database.clone()));
+
             Ok(bot)
         }));
 
@@ -0,0 +1,398 @@
+use std::collections::HashMap;
+
+use redis::{Commands, Connection};
+use serenity::builder::{CreateComponents, CreateEmbed};
+use serenity::http::{Http, StatusCode};
+use serenity::model::application::component::ButtonStyle;
+use serenity::model::id::{ChannelId, GuildId};
+use serenity::prelude::{Mentionable, SerenityError};
+use serenity::utils::Colour;
+use tokio::sync::MutexGuard;
+use tracing::{debug, info, warn};
+
+use crate::{events, identifiers, utils, Error};
+use crate::models::{OnboardingPause, Panel, PersistentMessage, RegisteredMessage, RoleGroup, RoleMenu, RoleMenuEntry, RoleMenuStyle, RoleSelection};
+
+/// Maximum number of buttons in an action row, as enforced by Discord.
+const ACTION_ROW_MAX_BUTTONS: usize = 5;
+
+/// Offset basis and prime of the 64-bit FNV-1a hash.
+const FNV_OFFSET_BASIS: u64 = 0xcbf29ce484222325;
+const FNV_PRIME: u64 = 0x100000001b3;
+
+/// Message as it should currently look, rendered from the guild's configuration.
+struct RenderedMessage {
+    channel: ChannelId,
+    embed: CreateEmbed,
+    components: CreateComponents,
+}
+
+impl RenderedMessage {
+    /// Hash the content of the message, to find out whether the posted message is outdated.
+    /// Embeds and components are serialized to JSON objects with sorted keys, so the hash only
+    /// depends on their content. FNV-1a is used as hashes are stored, and must stay the same
+    /// across Rust releases unlike the standard library's.
+    fn hash(&self) -> Result<u64, Error> {
+        let content = serde_json::to_string(&serde_json::json!({
+            "embed": self.embed.0,
+            "components": self.components.0,
+        }))?;
+
+        Ok(content.bytes().fold(FNV_OFFSET_BASIS, |hash, byte| (hash ^ u64::from(byte)).wrapping_mul(FNV_PRIME)))
+    }
+}
+
+/// Check every persistent message of a guild, posting again the ones that were deleted and
+/// editing in place the ones that are outdated.
+pub async fn verify_persistent_messages<'a>(http: &Http, database: &mut MutexGuard<'a, Connection>, guild_id: &GuildId) -> Result<(), Error> {
+    let guild_key = format!("guild:{}", guild_id);
+    let registry_key = format!("persistent_messages:{}", guild_id);
+
+    // Adopt the welcome message posted before the registry existed, rather than posting another one
+    let legacy_welcome_message: Option<u64> = database.hget(&guild_key, "welcome_message")?;
+
+    if let Some(legacy_welcome_message) = legacy_welcome_message {
+        let registered_message = RegisteredMessage {
+            channel: ChannelId(database.hget(&guild_key, "welcome_channel")?),
+            message: legacy_welcome_message.into(),
+            hash: 0,
+        };
+
+        let _: () = database.hset(&registry_key, PersistentMessage::Welcome.key(), serde_json::to_string(&registered_message)?)?;
+        let _: () = database.hdel(&guild_key, "welcome_message")?;
+    }
+
+    let keys: Vec<String> = database.hkeys(&registry_key)?;
+    let mut persistent_messages = vec![PersistentMessage::Welcome];
+
+    for key in keys {
+        match PersistentMessage::from_key(&key) {
+            Some(persistent_message) if !persistent_messages.contains(&persistent_message) => persistent_messages.push(persistent_message),
+            Some(_) => (),
+            None => warn!("unknown persistent message {} in guild {}", key, guild_id),
+        }
+    }
+
+    for persistent_message in persistent_messages {
+        if let Err(error) = publish(http, database, guild_id, &persistent_message).await {
+            warn!("could not verify the {} of guild {}: {}", persistent_message, guild_id, error);
+        }
+    }
+
+    Ok(())
+}
+
+/// Post a persistent message, or bring the already posted one up to date. The message is posted
+/// again if it was deleted or moved to another channel, and removed if it should no longer exist.
+pub async fn publish<'a>(http: &Http, database: &mut MutexGuard<'a, Connection>, guild_id: &GuildId, persistent_message: &PersistentMessage) -> Result<(), Error> {
+    let rendered_message = match render(database, guild_id, persistent_message)? {
+        Some(rendered_message) => rendered_message,
+        None => return remove(http, database, guild_id, persistent_message).await,
+    };
+    let hash = rendered_message.hash()?;
+    let registered_message = registered_message(database, guild_id, persistent_message)?;
+
+    if let Some(registered_message) = registered_message {
+        if registered_message.channel == rendered_message.channel {
+            let result = if registered_message.hash == hash {
+                registered_message.channel.message(http, registered_message.message).await.map(|_| ())
+            } else {
+                registered_message.channel.edit_message(http, registered_message.message, |message| {
+                    message
+                        .set_embed(rendered_message.embed.clone())
+                        .set_components(rendered_message.components.clone())
+                }).await.map(|_| ())
+            };
+
+            match result {
+                Ok(()) => {
+                    if registered_message.hash != hash {
+                        info!("updated the {} of guild {}", persistent_message, guild_id);
+
+                        register(database, guild_id, persistent_message, &RegisteredMessage { hash, ..registered_message })?;
+                    }
+
+                    return Ok(());
+                },
+                Err(SerenityError::Http(error)) if error.status_code() == Some(StatusCode::NOT_FOUND) => {
+                    info!("the {} of guild {} was deleted, posting it again", persistent_message, guild_id);
+                },
+                Err(error) => return Err(error.into()),
+            }
+        } else {
+            delete_message(http, &registered_message).await?;
+        }
+    }
+
+    let message = rendered_message.channel.send_message(http, |message| {
+        message
+            .set_embed(rendered_message.embed)
+            .set_components(rendered_message.components)
+    }).await?;
+
+    debug!("posted the {} of guild {}", persistent_message, guild_id);
+
+    register(database, guild_id, persistent_message, &RegisteredMessage {
+        channel: rendered_message.channel,
+        message: message.id,
+        hash,
+    })
+}
+
+/// Bring a persistent message up to date, only if it was already posted.
+pub async fn refresh<'a>(http: &Http, database: &mut MutexGuard<'a, Connection>, guild_id: &GuildId, persistent_message: &PersistentMessage) -> Result<(), Error> {
+    if registered_message(database, guild_id, persistent_message)?.is_some() {
+        publish(http, database, guild_id, persistent_message).await?;
+    }
+
+    Ok(())
+}
+
+/// Delete a persistent message and stop keeping it up to date.
+pub async fn remove<'a>(http: &Http, database: &mut MutexGuard<'a, Connection>, guild_id: &GuildId, persistent_message: &PersistentMessage) -> Result<(), Error> {
+    if let Some(registered_message) = registered_message(database, guild_id, persistent_message)? {
+        delete_message(http, &registered_message).await?;
+
+        let _: () = database.hdel(format!("persistent_messages:{}", guild_id), persistent_message.key())?;
+    }
+
+    Ok(())
+}
+
+/// Get where a persistent message was posted, if it was.
+pub fn registered_message(database: &mut Connection, guild_id: &GuildId, persistent_message: &PersistentMessage) -> Result<Option<RegisteredMessage>, Error> {
+    let registered_message: Option<String> = database.hget(format!("persistent_messages:{}", guild_id), persistent_message.key())?;
+
+    registered_message
+        .map(|registered_message| serde_json::from_str(&registered_message).map_err(Error::from))
+        .transpose()
+}
+
+fn register(database: &mut Connection, guild_id: &GuildId, persistent_message: &PersistentMessage, registered_message: &RegisteredMessage) -> Result<(), Error> {
+    let _: () = database.hset(format!("persistent_messages:{}", guild_id), persistent_message.key(), serde_json::to_string(registered_message)?)?;
+
+    Ok(())
+}
+
+async fn delete_message(http: &Http, registered_message: &RegisteredMessage) -> Result<(), Error> {
+    match registered_message.channel.delete_message(http, registered_message.message).await {
+        Err(SerenityError::Http(error)) if error.status_code() == Some(StatusCode::NOT_FOUND) => Ok(()),
+        result => result.map_err(Error::from),
+    }
+}
+
+/// Render a persistent message from the guild's configuration, or nothing if it should not be
+/// posted, e.g. because its role menu was deleted.
+fn render(database: &mut Connection, guild_id: &GuildId, persistent_message: &PersistentMessage) -> Result<Option<RenderedMessage>, Error> {
+    let mut embed = CreateEmbed::default();
+    let mut components = CreateComponents::default();
+
+    let channel = match persistent_message {
+        PersistentMessage::Welcome => {
+            let pause = events::onboarding_pause(database, guild_id)?;
+
+            welcome_embed(pause.as_ref(), &mut embed);
+            welcome_components(&mut components);
+
+            ChannelId(database.hget(format!("guild:{}", guild_id), "welcome_channel")?)
+        },
+        PersistentMessage::Rules => {
+            let onboarding_key = format!("onboarding:{}", guild_id);
+            let rules_channel: Option<u64> = database.hget(&onboarding_key, "rules_channel")?;
+            let rules: Option<String> = database.hget(&onboarding_key, "rules")?;
+            let rules_version: Option<String> = database.hget(&onboarding_key, "rules_version")?;
+
+            let (rules_channel, rules) = match (rules_channel, rules) {
+                (Some(rules_channel), Some(rules)) => (ChannelId(rules_channel), rules),
+                _ => return Ok(None),
+            };
+
+            embed
+                .colour(Colour::BLITZ_BLUE)
+                .title("📜 Server rules")
+                .description(rules)
+                .footer(|footer| footer.text(format!("Version {}", rules_version.unwrap_or_else(|| "1".into()))));
+
+            rules_channel
+        },
+        PersistentMessage::RoleMenu(name) => {
+            let role_menu: RoleMenu = match utils::role_selection(database, *guild_id, name)? {
+                Some(role_menu) => role_menu,
+                None => return Ok(None),
+            };
+
+            role_selection_embed(&role_menu, &mut embed);
+            role_menu_components(&role_menu, &mut components);
+
+            ChannelId(database.hget(format!("guild:{}", guild_id), "role_assignment_channel")?)
+        },
+        PersistentMessage::RoleGroup(name) => {
+            let role_group: RoleGroup = match utils::role_selection(database, *guild_id, name)? {
+                Some(role_group) => role_group,
+                None => return Ok(None),
+            };
+
+            role_selection_embed(&role_group, &mut embed);
+            role_buttons(&format!("{}:{}", identifiers::ROLE_GROUP, role_group.name), &role_group.roles, &mut components);
+
+            ChannelId(database.hget(format!("guild:{}", guild_id), "role_assignment_channel")?)
+        },
+        PersistentMessage::Panel(name) => {
+            let panel: Option<String> = database.hget(format!("panels:{}", guild_id), name)?;
+            let panel: Panel = match panel {
+                Some(panel) => serde_json::from_str(&panel)?,
+                None => return Ok(None),
+            };
+
+            embed
+                .colour(Colour::BLURPLE)
+                .title(&panel.title)
+                .description(&panel.content);
+
+            panel.channel
+        },
+    };
+
+    Ok(Some(RenderedMessage {
+        channel,
+        embed,
+        components,
+    }))
+}
+
+/// Get the panels defined in a guild, sorted by name.
+pub fn panels(database: &mut Connection, guild_id: &GuildId) -> Result<Vec<Panel>, Error> {
+    let panels: HashMap<String, String> = database.hgetall(format!("panels:{}", guild_id))?;
+    let mut panels = panels.values()
+        .map(|panel| serde_json::from_str(panel).map_err(Error::from))
+        .collect::<Result<Vec<Panel>, Error>>()?;
+
+    panels.sort_by(|a, b| a.name.cmp(&b.name));
+
+    Ok(panels)
+}
+
+fn welcome_embed<'a>(pause: Option<&OnboardingPause>, embed: &'a mut CreateEmbed) -> &'a mut CreateEmbed {
+    embed
+        .colour(Colour::BLITZ_BLUE)
+        .title("Welcome to Transpouce! 👋")
+        .description(
+            "This server is a safe space for discussion and exchange among trans and/or questioning people who are living in the Netherlands. It is open to 18+ people only, and is not tied to any existing organization, association or group.
+
+The main language of the server is English.
+
+To keep our space safe and gezellig, we have a simple verification process for new members in place. By clicking the button below, you'll be added to a private channel with the server staff, where we'll ask you some questions and get to know each other a little! <:transkitty:1000713242236178442>"
+        );
+
+    if let Some(pause) = pause {
+        embed.field("🚧 Applications closed", pause.notice(), false);
+    }
+
+    embed
+}
+
+fn welcome_components(components: &mut CreateComponents) -> &mut CreateComponents {
+    components.create_action_row(|row| {
+        row.create_button(|button| {
+            button
+                .custom_id(identifiers::ONBOARDING_START)
+                .style(ButtonStyle::Primary)
+                .emoji('🚪')
+                .label("Enter")
+        })
+    })
+}
+
+/// Build the embed of a role menu or role group, listing the roles it offers.
+fn role_selection_embed<'a, T: RoleSelection>(role_selection: &T, embed: &'a mut CreateEmbed) -> &'a mut CreateEmbed {
+    let mut description = role_selection.description().unwrap_or_default().to_owned();
+
+    for entry in role_selection.roles() {
+        if !description.is_empty() {
+            description.push('\n');
+        }
+
+        match &entry.emoji {
+            Some(emoji) => description.push_str(&format!("{} {}", emoji, entry.role.mention())),
+            None => description.push_str(&entry.role.mention().to_string()),
+        }
+    }
+
+    embed
+        .colour(Colour::BLURPLE)
+        .title(role_selection.title())
+        .description(description);
+
+    let constraints = role_selection.constraints();
+
+    if !constraints.is_empty() {
+        embed.footer(|footer| footer.text(constraints.join(" · ")));
+    }
+
+    embed
+}
+
+fn role_menu_components<'a>(role_menu: &RoleMenu, components: &'a mut CreateComponents) -> &'a mut CreateComponents {
+    match role_menu.style {
+        RoleMenuStyle::Buttons => {
+            role_buttons(&format!("{}:{}", identifiers::ROLE_MENU, role_menu.name), &role_menu.roles, components);
+        },
+        RoleMenuStyle::Select if !role_menu.roles.is_empty() => {
+            components.create_action_row(|row| {
+                row.create_select_menu(|menu| {
+                    menu
+                        .custom_id(format!("{}:{}", identifiers::ROLE_MENU, role_menu.name))
+                        .placeholder("Pick your roles")
+                        .min_values(0)
+                        .max_values(role_menu.roles.len() as u64)
+                        .options(|options| {
+                            for entry in &role_menu.roles {
+                                options.create_option(|option| {
+                                    option
+                                        .label(&entry.label)
+                                        .value(entry.role);
+
+                                    if let Some(emoji) = entry.emoji.as_deref().and_then(utils::parse_emoji) {
+                                        option.emoji(emoji);
+                                    }
+
+                                    option
+                                });
+                            }
+
+                            options
+                        })
+                })
+            });
+        },
+        RoleMenuStyle::Select => (),
+    }
+
+    components
+}
+
+/// Add a button for each role to a message, with custom IDs made of the given prefix and the role.
+fn role_buttons<'a>(custom_id_prefix: &str, entries: &[RoleMenuEntry], components: &'a mut CreateComponents) -> &'a mut CreateComponents {
+    for entries in entries.chunks(ACTION_ROW_MAX_BUTTONS) {
+        components.create_action_row(|row| {
+            for entry in entries {
+                row.create_button(|button| {
+                    button
+                        .custom_id(format!("{}:{}", custom_id_prefix, entry.role))
+                        .style(ButtonStyle::Secondary)
+                        .label(&entry.label);
+
+                    if let Some(emoji) = entry.emoji.as_deref().and_then(utils::parse_emoji) {
+                        button.emoji(emoji);
+                    }
+
+                    button
+                });
+            }
+
+            row
+        });
+    }
+
+    components
+}
@@ -1,8 +1,88 @@
+use std::fmt;
+
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
 use serenity::model::channel::{Channel, ChannelCategory, GuildChannel};
 use serenity::model::guild::Role;
+use serenity::model::id::{ChannelId, GuildId, MessageId, RoleId, UserId};
+use serenity::model::Timestamp;
 
 use crate::Error;
 
+/// Where validation discussions with new members take place.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, poise::ChoiceParameter)]
+pub enum OnboardingMode {
+    #[default]
+    #[name = "channel"]
+    Channel,
+    #[name = "thread"]
+    Thread,
+}
+
+/// State of a setting that can be turned on or off.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, poise::ChoiceParameter)]
+pub enum Toggle {
+    #[name = "on"]
+    On,
+    #[name = "off"]
+    Off,
+}
+
+/// How the roles of a role menu are offered to members.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Deserialize, Serialize, poise::ChoiceParameter)]
+#[serde(rename_all = "snake_case")]
+pub enum RoleMenuStyle {
+    #[default]
+    #[name = "buttons"]
+    Buttons,
+    #[name = "select"]
+    Select,
+}
+
+/// Common pronouns offered as presets, free text covers everything else.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, poise::ChoiceParameter)]
+pub enum PronounsPreset {
+    #[name = "she/her"]
+    SheHer,
+    #[name = "he/him"]
+    HeHim,
+    #[name = "they/them"]
+    TheyThem,
+    #[name = "she/they"]
+    SheThey,
+    #[name = "he/they"]
+    HeThey,
+    #[name = "any pronouns"]
+    Any,
+    #[name = "ask me"]
+    AskMe,
+}
+
+/// Closure of applications in a guild, e.g. while staff are on holidays.
+#[derive(Debug, Default)]
+pub struct OnboardingPause {
+    pub until: Option<i64>,
+    pub message: Option<String>,
+}
+
+impl OnboardingPause {
+    /// Describe the closure to members trying to apply.
+    pub fn notice(&self) -> String {
+        let mut notice = String::from("Applications to join the server are currently closed");
+
+        match self.until {
+            Some(until) => notice.push_str(&format!(" until <t:{}:f>.", until)),
+            None => notice.push('.'),
+        }
+
+        if let Some(message) = &self.message {
+            notice.push_str(&format!("\n\n{}", message));
+        }
+
+        notice
+    }
+}
+
 /// Guild configuration for the bot.
 #[derive(Default)]
 pub struct GuildConfiguration {
@@ -13,29 +93,18 @@ pub struct GuildConfiguration {
     pub role_assignment_channel: Option<GuildChannel>,
     pub validation_category: Option<ChannelCategory>,
     pub welcome_channel: Option<GuildChannel>,
+    pub log_channel: Option<GuildChannel>,
 }
 
 impl GuildConfiguration {
-    pub fn new(admin_role: Role, validated_role: Role, introductions_channel: Channel, role_assignment_channel: Channel, validation_category: Channel, welcome_channel: Channel) -> Result<Self, Error> {
-        let introductions_channel = match introductions_channel {
-            Channel::Guild(c) => Some(c),
-            _ => { return Err(Error::from(format!("given introductions channel (id: {}) is not a guild channel", introductions_channel.id()))); },
-        };
-
-        let role_assignment_channel = match role_assignment_channel {
-            Channel::Guild(c) => Some(c),
-            _ => { return Err(Error::from(format!("given role assignment channel (id: {}) is not a guild channel", role_assignment_channel.id()))); },
-        };
-
-        let validation_category = match validation_category {
-            Channel::Category(c) => Some(c),
-            _ => { return Err(Error::from(format!("given validation channel (id: {}) is not a category channel", validation_category.id()))); },
-        };
-
-        let welcome_channel = match welcome_channel {
-            Channel::Guild(c) => Some(c),
-            _ => { return Err(Error::from(format!("given welcome channel (id: {}) is not a guild channel", welcome_channel.id()))); },
-        };
+    pub fn new(admin_role: Role, validated_role: Role, introductions_channel: Channel, role_assignment_channel: Channel, validation_category: Channel, welcome_channel: Channel, log_channel: Option<Channel>) -> Result<Self, Error> {
+        let introductions_channel = Some(Self::guild_channel(introductions_channel, "introductions channel")?);
+        let role_assignment_channel = Some(Self::guild_channel(role_assignment_channel, "role assignment channel")?);
+        let validation_category = Some(Self::category_channel(validation_category, "validation channel")?);
+        let welcome_channel = Some(Self::guild_channel(welcome_channel, "welcome channel")?);
+        let log_channel = log_channel
+            .map(|log_channel| Self::guild_channel(log_channel, "log channel"))
+            .transpose()?;
 
         Ok(Self {
             configured: true,
@@ -44,7 +113,312 @@ impl GuildConfiguration {
             introductions_channel,
             role_assignment_channel,
             validation_category,
-            welcome_channel
+            welcome_channel,
+            log_channel,
         })
     }
+
+    /// Check that a channel given for a setting is a guild channel.
+    pub fn guild_channel(channel: Channel, setting: &str) -> Result<GuildChannel, Error> {
+        match channel {
+            Channel::Guild(c) => Ok(c),
+            _ => Err(Error::from(format!("given {} (id: {}) is not a guild channel", setting, channel.id()))),
+        }
+    }
+
+    /// Check that a channel given for a setting is a category channel.
+    pub fn category_channel(channel: Channel, setting: &str) -> Result<ChannelCategory, Error> {
+        match channel {
+            Channel::Category(c) => Ok(c),
+            _ => Err(Error::from(format!("given {} (id: {}) is not a category channel", setting, channel.id()))),
+        }
+    }
+}
+
+/// Kind of event recorded in a member's onboarding history.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum HistoryEvent {
+    Application,
+    Approval,
+    Denial,
+    Revocation,
+    Vouch,
+}
+
+impl fmt::Display for HistoryEvent {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Self::Application => "Application",
+            Self::Approval => "Approval",
+            Self::Denial => "Denial",
+            Self::Revocation => "Revocation",
+            Self::Vouch => "Vouch",
+        })
+    }
+}
+
+/// Entry of a member's onboarding history in a guild.
+#[derive(Debug, Deserialize, Serialize)]
+pub struct HistoryEntry {
+    pub event: HistoryEvent,
+    pub timestamp: i64,
+    pub actor: Option<UserId>,
+    pub details: Option<String>,
+}
+
+impl HistoryEntry {
+    pub fn new(event: HistoryEvent, actor: Option<UserId>, details: Option<String>) -> Self {
+        Self {
+            event,
+            timestamp: Timestamp::now().unix_timestamp(),
+            actor,
+            details,
+        }
+    }
+}
+
+/// Step of the onboarding process counted towards the conversion statistics of invites.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum InviteConversion {
+    Application,
+    Approval,
+}
+
+impl InviteConversion {
+    /// Key of the guild's counts of this step per invite code.
+    pub fn key(&self, guild_id: GuildId) -> String {
+        match self {
+            Self::Application => format!("invite_applications:{}", guild_id),
+            Self::Approval => format!("invite_approvals:{}", guild_id),
+        }
+    }
+
+    /// Key of the guild's set of members already counted for this step.
+    pub fn counted_members_key(&self, guild_id: GuildId) -> String {
+        match self {
+            Self::Application => format!("invite_applicants:{}", guild_id),
+            Self::Approval => format!("invite_approved:{}", guild_id),
+        }
+    }
+}
+
+/// Note written by a staff member about a member of a guild.
+#[derive(Debug, Deserialize, Serialize)]
+pub struct Note {
+    pub id: u64,
+    pub author: UserId,
+    pub timestamp: i64,
+    pub content: String,
+}
+
+/// Set of roles members can assign themselves in the role assignment channel.
+#[derive(Debug, Deserialize, Serialize)]
+pub struct RoleMenu {
+    pub name: String,
+    pub title: String,
+    pub description: Option<String>,
+    pub style: RoleMenuStyle,
+    pub roles: Vec<RoleMenuEntry>,
+}
+
+/// Role offered in a role menu, with the label and emoji of its button or option.
+#[derive(Debug, Deserialize, Serialize)]
+pub struct RoleMenuEntry {
+    pub role: RoleId,
+    pub label: String,
+    pub emoji: Option<String>,
+}
+
+/// Set of roles members pick from a button panel in the role assignment channel, with
+/// constraints on which combinations they can have.
+#[derive(Debug, Deserialize, Serialize)]
+pub struct RoleGroup {
+    pub name: String,
+    pub title: String,
+    pub description: Option<String>,
+    pub single_choice: bool,
+    pub max_roles: Option<u64>,
+    pub validated_only: bool,
+    pub roles: Vec<RoleMenuEntry>,
+}
+
+/// Set of roles members pick themselves from a message posted in the role assignment channel,
+/// managed by admins through the same subcommands whatever its kind.
+pub trait RoleSelection: DeserializeOwned + Serialize {
+    /// Kind of selection, as shown to admins, e.g. "role menu".
+    const KIND: &'static str;
+    /// Kind of selection at the start of a sentence, e.g. "Role menu".
+    const TITLE: &'static str;
+    /// Command managing selections of this kind.
+    const COMMAND: &'static str;
+    /// Prefix of the key of the hash storing the selections of a guild by name.
+    const KEY_PREFIX: &'static str;
+    /// Maximum number of roles a selection can offer.
+    const MAX_ROLES: usize;
+
+    fn name(&self) -> &str;
+    fn title(&self) -> &str;
+    fn description(&self) -> Option<&str>;
+    fn roles(&self) -> &[RoleMenuEntry];
+    fn roles_mut(&mut self) -> &mut Vec<RoleMenuEntry>;
+    /// Persistent message showing the selection with the given name.
+    fn persistent_message(name: String) -> PersistentMessage;
+
+    /// Describe the constraints of the selection to members.
+    fn constraints(&self) -> Vec<String> {
+        Vec::new()
+    }
+}
+
+impl RoleSelection for RoleMenu {
+    const KIND: &'static str = "role menu";
+    const TITLE: &'static str = "Role menu";
+    const COMMAND: &'static str = "rolemenu";
+    const KEY_PREFIX: &'static str = "role_menus";
+    // Both select menus and messages are limited to 25 options or buttons
+    const MAX_ROLES: usize = 25;
+
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn title(&self) -> &str {
+        &self.title
+    }
+
+    fn description(&self) -> Option<&str> {
+        self.description.as_deref()
+    }
+
+    fn roles(&self) -> &[RoleMenuEntry] {
+        &self.roles
+    }
+
+    fn roles_mut(&mut self) -> &mut Vec<RoleMenuEntry> {
+        &mut self.roles
+    }
+
+    fn persistent_message(name: String) -> PersistentMessage {
+        PersistentMessage::RoleMenu(name)
+    }
+}
+
+impl RoleSelection for RoleGroup {
+    const KIND: &'static str = "role group";
+    const TITLE: &'static str = "Role group";
+    const COMMAND: &'static str = "rolegroup";
+    const KEY_PREFIX: &'static str = "role_groups";
+    // Messages are limited to 25 buttons
+    const MAX_ROLES: usize = 25;
+
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn title(&self) -> &str {
+        &self.title
+    }
+
+    fn description(&self) -> Option<&str> {
+        self.description.as_deref()
+    }
+
+    fn roles(&self) -> &[RoleMenuEntry] {
+        &self.roles
+    }
+
+    fn roles_mut(&mut self) -> &mut Vec<RoleMenuEntry> {
+        &mut self.roles
+    }
+
+    fn persistent_message(name: String) -> PersistentMessage {
+        PersistentMessage::RoleGroup(name)
+    }
+
+    fn constraints(&self) -> Vec<String> {
+        let mut constraints = Vec::new();
+
+        if self.single_choice {
+            constraints.push("Pick one".to_owned());
+        } else if let Some(max_roles) = self.max_roles {
+            constraints.push(format!("Pick up to {}", max_roles));
+        }
+
+        if self.validated_only {
+            constraints.push("Validated members only".to_owned());
+        }
+
+        constraints
+    }
+}
+
+/// Informational message defined by admins, e.g. a FAQ or a list of useful links.
+#[derive(Debug, Deserialize, Serialize)]
+pub struct Panel {
+    pub name: String,
+    pub channel: ChannelId,
+    pub title: String,
+    pub content: String,
+}
+
+/// Page of the guide sent to members once they are approved.
+#[derive(Debug, Deserialize, Serialize)]
+pub struct GuidePage {
+    pub title: String,
+    pub content: String,
+}
+
+/// Message the bot posted and keeps up to date on its own.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum PersistentMessage {
+    Welcome,
+    Rules,
+    RoleMenu(String),
+    RoleGroup(String),
+    Panel(String),
+}
+
+impl PersistentMessage {
+    /// Key of the message in a guild's registry of persistent messages.
+    pub fn key(&self) -> String {
+        match self {
+            Self::Welcome => "welcome".to_owned(),
+            Self::Rules => "rules".to_owned(),
+            Self::RoleMenu(name) => format!("role_menu:{}", name),
+            Self::RoleGroup(name) => format!("role_group:{}", name),
+            Self::Panel(name) => format!("panel:{}", name),
+        }
+    }
+
+    pub fn from_key(key: &str) -> Option<Self> {
+        match key.split_once(':') {
+            Some(("role_menu", name)) => Some(Self::RoleMenu(name.to_owned())),
+            Some(("role_group", name)) => Some(Self::RoleGroup(name.to_owned())),
+            Some(("panel", name)) => Some(Self::Panel(name.to_owned())),
+            None if key == "welcome" => Some(Self::Welcome),
+            None if key == "rules" => Some(Self::Rules),
+            _ => None,
+        }
+    }
+}
+
+impl fmt::Display for PersistentMessage {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Welcome => f.write_str("welcome message"),
+            Self::Rules => f.write_str("rules"),
+            Self::RoleMenu(name) => write!(f, "role menu `{}`", name),
+            Self::RoleGroup(name) => write!(f, "role group `{}`", name),
+            Self::Panel(name) => write!(f, "panel `{}`", name),
+        }
+    }
+}
+
+/// Location and content hash of a persistent message, as last posted or edited.
+#[derive(Debug, Deserialize, Serialize)]
+pub struct RegisteredMessage {
+    pub channel: ChannelId,
+    pub message: MessageId,
+    pub hash: u64,
 }
@@ -0,0 +1,361 @@
+use std::sync::Arc;
+use std::time::Duration;
+
+use redis::{Commands, Connection};
+use serenity::http::StatusCode;
+use serenity::model::guild::Member;
+use serenity::model::id::{ChannelId, GuildId, RoleId, UserId};
+use serenity::model::Timestamp;
+use serenity::prelude::{Mentionable, Mutex, SerenityError};
+use tokio::sync::MutexGuard;
+use tokio::time::Instant;
+use tracing::{debug, error, warn};
+
+use crate::{events, utils, Error};
+
+/// Sorted set of temporary roles, as `guild:user:role` members scored by their expiry time.
+pub const TEMPORARY_ROLES_KEY: &str = "temporary_roles";
+
+/// Set of guilds that enabled their inactivity policy, reminding and kicking members who never
+/// start onboarding.
+pub const INACTIVITY_POLICIES_KEY: &str = "inactivity_policies";
+
+/// Interval between two runs of the scheduled tasks.
+const SCHEDULER_INTERVAL: Duration = Duration::from_secs(60);
+
+/// Interval between two checks for members who never started onboarding, which requires
+/// fetching every member of the guilds with an inactivity policy.
+const INACTIVITY_SWEEP_INTERVAL: Duration = Duration::from_secs(3600);
+
+/// Maximum number of members fetched per request, as enforced by Discord.
+const MEMBERS_PAGE_SIZE: u64 = 1000;
+
+const SECONDS_PER_DAY: i64 = 86400;
+
+/// Members who joined a guild but never started onboarding, as of an inactivity check.
+pub struct InactiveMembers {
+    pub to_remind: Vec<Member>,
+    pub to_kick: Vec<Member>,
+}
+
+/// Run time-based tasks, such as reopening applications, at regular intervals.
+pub async fn scheduler(ctx: serenity::client::Context, database: Arc<Mutex<Connection>>) {
+    let mut interval = tokio::time::interval(SCHEDULER_INTERVAL);
+    let mut next_inactivity_sweep = Instant::now();
+
+    loop {
+        interval.tick().await;
+
+        if let Err(error) = resume_paused_onboarding(&ctx, &database).await {
+            error!("error while resuming paused onboarding: {:?}", error);
+        }
+
+        if let Err(error) = expire_temporary_roles(&ctx, &database).await {
+            error!("error while removing expired temporary roles: {:?}", error);
+        }
+
+        if let Err(error) = send_introduction_reminders(&ctx, &database).await {
+            error!("error while reminding members to introduce themselves: {:?}", error);
+        }
+
+        if Instant::now() >= next_inactivity_sweep {
+            next_inactivity_sweep = Instant::now() + INACTIVITY_SWEEP_INTERVAL;
+
+            if let Err(error) = sweep_inactive_members(&ctx, &database).await {
+                error!("error while sweeping members who never started onboarding: {:?}", error);
+            }
+        }
+    }
+}
+
+async fn resume_paused_onboarding(ctx: &serenity::client::Context, database: &Mutex<Connection>) -> Result<(), Error> {
+    let mut database = database.lock().await;
+    let now = Timestamp::now().unix_timestamp();
+    let guilds: Vec<u64> = database.zrangebyscore(events::SCHEDULED_RESUMES_KEY, "-inf", now)?;
+
+    for guild_id in guilds.into_iter().map(GuildId) {
+        // One guild failing to resume must not hold back the others
+        if let Err(error) = events::resume_onboarding(ctx, &mut database, &guild_id).await {
+            error!("could not resume onboarding in guild {}: {:?}", guild_id, error);
+
+            continue;
+        }
+
+        if let Err(error) = utils::log_to_staff(&ctx.http, &mut database, guild_id, "🔓 Applications were automatically reopened").await {
+            warn!("could not log the reopening of applications to the staff of guild {}: {:?}", guild_id, error);
+        }
+    }
+
+    Ok(())
+}
+
+/// Build the member of the temporary roles sorted set for a role given to a member of a guild.
+pub fn temporary_role_key(guild_id: GuildId, user_id: UserId, role_id: RoleId) -> String {
+    format!("{}:{}:{}", guild_id, user_id, role_id)
+}
+
+async fn expire_temporary_roles(ctx: &serenity::client::Context, database: &Mutex<Connection>) -> Result<(), Error> {
+    let mut database = database.lock().await;
+    let now = Timestamp::now().unix_timestamp();
+    let temporary_roles: Vec<String> = database.zrangebyscore(TEMPORARY_ROLES_KEY, "-inf", now)?;
+
+    for temporary_role in temporary_roles {
+        let ids = temporary_role.split(':').map(str::parse).collect::<Result<Vec<u64>, _>>();
+        let (guild_id, user_id, role_id) = match ids.as_deref() {
+            Ok([guild_id, user_id, role_id]) => (GuildId(*guild_id), UserId(*user_id), RoleId(*role_id)),
+            _ => {
+                error!("invalid temporary role {}, dropping it", temporary_role);
+                let _: () = database.zrem(TEMPORARY_ROLES_KEY, &temporary_role)?;
+
+                continue;
+            },
+        };
+
+        match ctx.http.remove_member_role(guild_id.0, user_id.0, role_id.0, Some("Temporary role expired")).await {
+            Ok(()) => {
+                let _: () = database.zrem(TEMPORARY_ROLES_KEY, &temporary_role)?;
+
+                let logged = utils::log_to_staff(&ctx.http, &mut database, guild_id, format!(
+                    "⌛ Removed the temporary role {} from {}",
+                    role_id.mention(),
+                    user_id.mention(),
+                )).await;
+
+                if let Err(error) = logged {
+                    warn!("could not log the expiry of temporary role {} of member {} to the staff of guild {}: {:?}", role_id, user_id, guild_id, error);
+                }
+            },
+            // Members who left or roles that were deleted in the meantime have nothing to remove
+            Err(SerenityError::Http(error)) if error.status_code() == Some(StatusCode::NOT_FOUND) => {
+                debug!("temporary role {} of member {} in guild {} no longer exists", role_id, user_id, guild_id);
+
+                let _: () = database.zrem(TEMPORARY_ROLES_KEY, &temporary_role)?;
+            },
+            Err(error) => {
+                // Retry on the next run
+                error!("could not remove temporary role {} from member {} in guild {}: {}", role_id, user_id, guild_id, error);
+            },
+        }
+    }
+
+    Ok(())
+}
+
+async fn send_introduction_reminders(ctx: &serenity::client::Context, database: &Mutex<Connection>) -> Result<(), Error> {
+    let mut database = database.lock().await;
+    let now = Timestamp::now().unix_timestamp();
+    let reminders: Vec<String> = database.zrangebyscore(events::SCHEDULED_INTRODUCTION_REMINDERS_KEY, "-inf", now)?;
+
+    for reminder in reminders {
+        let _: () = database.zrem(events::SCHEDULED_INTRODUCTION_REMINDERS_KEY, &reminder)?;
+
+        let ids = reminder.split(':').map(str::parse).collect::<Result<Vec<u64>, _>>();
+        let (guild_id, user_id) = match ids.as_deref() {
+            Ok([guild_id, user_id]) => (GuildId(*guild_id), UserId(*user_id)),
+            _ => {
+                error!("invalid introduction reminder {}, dropping it", reminder);
+
+                continue;
+            },
+        };
+
+        if database.hexists(format!("introductions:{}", guild_id), user_id.as_u64())? {
+            continue;
+        }
+
+        let introductions_channel: u64 = database.hget(format!("guild:{}", guild_id), "introductions_channel")?;
+        let guild_name = ctx.cache.guild_field(guild_id, |guild| guild.name.clone()).unwrap_or_else(|| "the server".into());
+        let content = format!(
+            "👋 We'd love to get to know you better on **{}**! Tell us a bit about yourself in {} whenever you feel like it.",
+            guild_name,
+            ChannelId(introductions_channel).mention(),
+        );
+        let direct_message = match user_id.create_dm_channel(&ctx.http).await {
+            Ok(channel) => channel.say(&ctx.http, content).await.map(|_| ()),
+            Err(error) => Err(error),
+        };
+
+        if let Err(error) = direct_message {
+            debug!("could not remind member {} of guild {} to introduce themselves: {}", user_id, guild_id, error);
+        }
+    }
+
+    Ok(())
+}
+
+async fn sweep_inactive_members(ctx: &serenity::client::Context, database: &Mutex<Connection>) -> Result<(), Error> {
+    let guilds: Vec<u64> = database.lock().await.smembers(INACTIVITY_POLICIES_KEY)?;
+
+    for guild_id in guilds.into_iter().map(GuildId) {
+        if let Err(error) = sweep_guild_inactive_members(ctx, database, &guild_id).await {
+            error!("error while sweeping members of guild {} who never started onboarding: {:?}", guild_id, error);
+        }
+    }
+
+    Ok(())
+}
+
+/// Remind and kick the members of a guild who never started onboarding. The database is only
+/// locked while reading and writing it, not while members are fetched, messaged or kicked.
+async fn sweep_guild_inactive_members(ctx: &serenity::client::Context, database: &Mutex<Connection>, guild_id: &GuildId) -> Result<(), Error> {
+    let (kick_days, welcome_channel) = {
+        let mut database = database.lock().await;
+        let kick_days: Option<u64> = database.hget(format!("onboarding:{}", guild_id), "inactivity_kick_days")?;
+        let welcome_channel: u64 = database.hget(format!("guild:{}", guild_id), "welcome_channel")?;
+
+        (kick_days, ChannelId(welcome_channel))
+    };
+    let guild_name = ctx.cache.guild_field(guild_id, |guild| guild.name.clone()).unwrap_or_else(|| "the server".into());
+    let inactive_members = inactive_members(ctx, database, guild_id).await?;
+
+    for member in &inactive_members.to_remind {
+        let mut content = format!(
+            "👋 You joined **{}** a while ago but haven't requested access yet. Head over to {} and click the button to get started!",
+            guild_name,
+            welcome_channel.mention(),
+        );
+
+        if let Some(kick_days) = kick_days {
+            content.push_str(&format!(" Members who don't apply within {} days of joining are removed from the server.", kick_days));
+        }
+
+        if let Err(error) = member.user.direct_message(&ctx.http, |message| message.content(content)).await {
+            debug!("could not remind member {} to start onboarding: {}", member.user.id, error);
+        }
+    }
+
+    let mut kicked = Vec::new();
+
+    for member in &inactive_members.to_kick {
+        match member.kick_with_reason(&ctx.http, "Did not start onboarding in time").await {
+            Ok(()) => kicked.push(member.user.id.mention().to_string()),
+            Err(error) => error!("could not kick member {} who never started onboarding: {}", member.user.id, error),
+        }
+    }
+
+    let mut database = database.lock().await;
+    let now = Timestamp::now().unix_timestamp();
+
+    for member in &inactive_members.to_remind {
+        let _: () = database.hset(format!("validation:{}:{}", guild_id, member.user.id), "inactivity_reminded_at", now)?;
+    }
+
+    if !inactive_members.to_remind.is_empty() {
+        utils::log_to_staff(&ctx.http, &mut database, *guild_id, format!(
+            "⏰ Reminded {} members who haven't started onboarding yet",
+            inactive_members.to_remind.len(),
+        )).await?;
+    }
+
+    if !kicked.is_empty() {
+        utils::log_to_staff(&ctx.http, &mut database, *guild_id, format!(
+            "👢 Kicked {} members who never started onboarding: {}",
+            kicked.len(),
+            kicked.join(", "),
+        )).await?;
+    }
+
+    Ok(())
+}
+
+/// Find the members of a guild who should be reminded to start onboarding, or kicked for never
+/// doing so, according to the guild's inactivity policy. Validated members, staff members and
+/// bots are never considered. The database is only locked while checking each page of members,
+/// so that fetching the members of large guilds doesn't hold up the rest of the bot.
+pub async fn inactive_members(ctx: &serenity::client::Context, database: &Mutex<Connection>, guild_id: &GuildId) -> Result<InactiveMembers, Error> {
+    let (remind_days, kick_days, validated_role) = {
+        let mut database = database.lock().await;
+        let onboarding_key = format!("onboarding:{}", guild_id);
+        let remind_days: Option<i64> = database.hget(&onboarding_key, "inactivity_remind_days")?;
+        let kick_days: Option<i64> = database.hget(&onboarding_key, "inactivity_kick_days")?;
+        let validated_role: u64 = database.hget(format!("guild:{}", guild_id), "validated_role")?;
+
+        (remind_days, kick_days, RoleId(validated_role))
+    };
+    let now = Timestamp::now().unix_timestamp();
+    let mut inactive_members = InactiveMembers {
+        to_remind: Vec::new(),
+        to_kick: Vec::new(),
+    };
+    let mut after = None;
+
+    loop {
+        let members = guild_id.members(&ctx.http, Some(MEMBERS_PAGE_SIZE), after).await?;
+        let mut database = database.lock().await;
+
+        after = members.last().map(|member| member.user.id);
+
+        for member in members.iter() {
+            if member.user.bot ||
+                member.roles.contains(&validated_role) ||
+                utils::member_is_staff(&ctx.cache, &mut database, member)? {
+                continue;
+            }
+
+            let joined_at = match member.joined_at {
+                Some(joined_at) => joined_at.unix_timestamp(),
+                None => continue,
+            };
+
+            if started_onboarding(&mut database, guild_id, &member.user.id, joined_at).await? {
+                continue;
+            }
+
+            let days = (now - joined_at) / SECONDS_PER_DAY;
+
+            if let Some(kick_days) = kick_days {
+                if days >= kick_days {
+                    inactive_members.to_kick.push(member.clone());
+
+                    continue;
+                }
+            }
+
+            if let Some(remind_days) = remind_days {
+                let validation_key = format!("validation:{}:{}", guild_id, member.user.id);
+                let reminded_at: Option<i64> = database.hget(&validation_key, "inactivity_reminded_at")?;
+                // Reminders sent before the member left and joined again don't count
+                let reminded = match reminded_at {
+                    Some(reminded_at) => reminded_at >= joined_at,
+                    None => false,
+                };
+
+                if days >= remind_days && !reminded {
+                    inactive_members.to_remind.push(member.clone());
+                }
+            }
+        }
+
+        if (members.len() as u64) < MEMBERS_PAGE_SIZE {
+            break;
+        }
+    }
+
+    Ok(inactive_members)
+}
+
+/// Check whether a member did anything towards onboarding since they last joined: accepting the
+/// rules, applying, or waiting for an application slot.
+async fn started_onboarding<'a>(database: &mut MutexGuard<'a, Connection>, guild_id: &GuildId, user_id: &UserId, joined_at: i64) -> Result<bool, Error> {
+    if events::pending_validation(database, guild_id, user_id).await?.is_some() {
+        return Ok(true);
+    }
+
+    let waitlist: Vec<u64> = database.lrange(format!("waitlist:{}", guild_id), 0, -1)?;
+
+    if waitlist.contains(user_id.as_u64()) {
+        return Ok(true);
+    }
+
+    let rules_accepted_at: Option<i64> = database.hget(format!("validation:{}:{}", guild_id, user_id), "rules_accepted_at")?;
+
+    if let Some(rules_accepted_at) = rules_accepted_at {
+        if rules_accepted_at >= joined_at {
+            return Ok(true);
+        }
+    }
+
+    let history = utils::member_history(database, *guild_id, *user_id)?;
+
+    Ok(history.iter().any(|entry| entry.timestamp >= joined_at))
+}
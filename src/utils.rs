@@ -0,0 +1,660 @@
+use std::fmt::Display;
+
+use redis::{Commands, Connection};
+use serenity::builder::{CreateComponents, CreateEmbed};
+use serenity::model::application::component::ButtonStyle;
+use serenity::cache::Cache;
+use serenity::http::Http;
+use serenity::model::channel::{Channel, ChannelCategory, ChannelType, PermissionOverwriteType, ReactionType};
+use serenity::model::guild::{Guild, Member, Role};
+use serenity::model::permissions::Permissions;
+use serenity::model::id::{ChannelId, GuildId, RoleId, UserId};
+use serenity::model::user::User;
+use serenity::prelude::Mentionable;
+use serenity::utils::Colour;
+use tokio::sync::MutexGuard;
+use tracing::warn;
+
+use crate::{identifiers, Context, Error};
+use crate::models::{GuidePage, HistoryEntry, InviteConversion, Note, OnboardingMode, RoleSelection};
+
+/// Default template for the names of validation channels and threads.
+pub const DEFAULT_CHANNEL_NAME_TEMPLATE: &str = "{username}";
+
+/// Maximum length of a channel name, as enforced by Discord.
+const CHANNEL_NAME_MAX_LENGTH: usize = 100;
+
+/// Format a user's name for display, accounting for users that migrated to unique usernames
+/// and no longer have a discriminator.
+pub fn tag(name: &str, discriminator: u16) -> String {
+    if discriminator == 0 {
+        name.to_owned()
+    } else {
+        format!("{}#{:04}", name, discriminator)
+    }
+}
+
+/// Maximum number of fields in an embed, as enforced by Discord.
+pub const EMBED_MAX_FIELDS: usize = 25;
+
+/// Maximum length of the value of an embed field, as enforced by Discord.
+const EMBED_FIELD_MAX_LENGTH: usize = 1024;
+
+/// Maximum total length of the fields of an embed, leaving room for its title and footer within
+/// the 6000 characters Discord allows for a whole embed.
+const EMBED_FIELDS_MAX_TOTAL_LENGTH: usize = 5500;
+
+/// Maximum length of the name of a role menu, role group or panel, keeping component custom IDs
+/// well under Discord's limit.
+const NAME_MAX_LENGTH: usize = 32;
+
+/// Normalize the name of a role menu, role group or panel, or reply with why it isn't valid.
+pub async fn validated_name(ctx: Context<'_>, kind: &str, name: &str) -> Result<Option<String>, Error> {
+    let name = name.trim().to_lowercase();
+
+    if name.is_empty() || name.len() > NAME_MAX_LENGTH || !name.chars().all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_') {
+        poise::send_reply(ctx, |reply| {
+            reply
+                .content(format!("{} names must be at most {} letters, digits, dashes or underscores long.", kind, NAME_MAX_LENGTH))
+                .ephemeral(true)
+        }).await?;
+
+        return Ok(None);
+    }
+
+    Ok(Some(name))
+}
+
+/// Maximum number of code points in a unicode emoji, enough for the longest ZWJ sequences.
+const EMOJI_MAX_CODE_POINTS: usize = 16;
+
+/// Shorten a text to a maximum number of characters, ending it with an ellipsis if it was cut.
+pub fn truncate(text: &str, max_length: usize) -> String {
+    if text.chars().count() <= max_length {
+        return text.to_owned();
+    }
+
+    let mut truncated = text.chars().take(max_length.saturating_sub(1)).collect::<String>();
+
+    truncated.push('…');
+
+    truncated
+}
+
+/// Parse an emoji given by an admin, either a custom emoji such as `<:name:id>` or `<a:name:id>`,
+/// or a unicode emoji, possibly made of several code points joined together.
+pub fn parse_emoji(emoji: &str) -> Option<ReactionType> {
+    let emoji = emoji.trim();
+
+    if emoji.starts_with('<') {
+        return match emoji.parse::<ReactionType>() {
+            Ok(emoji @ ReactionType::Custom { .. }) => Some(emoji),
+            _ => None,
+        };
+    }
+
+    let is_pictographic = |c: char| matches!(c as u32,
+        0x00A9 | 0x00AE | 0x203C | 0x2049 | 0x2122 | 0x2139 | 0x24C2 | 0x3030 | 0x303D | 0x3297 | 0x3299 |
+        0x2190..=0x21FF | 0x2300..=0x23FF | 0x25A0..=0x27BF | 0x2900..=0x297F | 0x2B00..=0x2BFF |
+        0x1F000..=0x1FAFF
+    );
+    // Joiners, variation selectors, keycaps, skin tones and tags only modify a pictograph
+    let is_modifier = |c: char| matches!(c as u32,
+        0x200D | 0xFE0E | 0xFE0F | 0x20E3 | 0x1F3FB..=0x1F3FF | 0xE0020..=0xE007F
+    );
+    let is_keycap = emoji.ends_with('\u{20E3}') && matches!(emoji.chars().next(), Some(c) if c.is_ascii_digit() || c == '#' || c == '*');
+
+    if emoji.is_empty() || emoji.chars().count() > EMOJI_MAX_CODE_POINTS {
+        return None;
+    }
+
+    let valid = (is_keycap && emoji.chars().skip(1).all(is_modifier)) ||
+        (emoji.chars().any(is_pictographic) && emoji.chars().all(|c| is_pictographic(c) || is_modifier(c)));
+
+    valid.then(|| ReactionType::Unicode(emoji.to_owned()))
+}
+
+/// Parse a duration such as `3d`, `1h30m` or `90s` into a number of seconds.
+pub fn parse_duration(input: &str) -> Option<i64> {
+    let mut seconds = 0i64;
+    let mut number = String::new();
+
+    for c in input.trim().chars() {
+        if c.is_ascii_digit() {
+            number.push(c);
+
+            continue;
+        }
+
+        let unit = match c.to_ascii_lowercase() {
+            'w' => 604800,
+            'd' => 86400,
+            'h' => 3600,
+            'm' => 60,
+            's' => 1,
+            _ => return None,
+        };
+        let value: i64 = number.parse().ok()?;
+
+        seconds = seconds.checked_add(value.checked_mul(unit)?)?;
+        number.clear();
+    }
+
+    if !number.is_empty() || seconds == 0 {
+        return None;
+    }
+
+    Some(seconds)
+}
+
+/// Format a member's name for display, including their nickname in the guild if they have one.
+pub fn member_tag(member: &Member) -> String {
+    let tag = tag(&member.user.name, member.user.discriminator);
+
+    match &member.nick {
+        Some(nick) => format!("{}, {}", nick, tag),
+        None => tag,
+    }
+}
+
+/// Render the name of a validation channel from a template, replacing `{n}` with the guild's
+/// validation counter, `{username}` with the user's name and `{id}` with the user's ID.
+pub fn validation_channel_name(template: &str, counter: u64, user: &User) -> String {
+    let name = template
+        .replace("{n}", &counter.to_string())
+        .replace("{username}", &user.name)
+        .replace("{id}", &user.id.to_string());
+    let name = sanitize_channel_name(&name);
+
+    if name.is_empty() {
+        format!("verify-{}", user.id)
+    } else {
+        name
+    }
+}
+
+/// Make a string follow Discord's text channel naming rules: lowercase, no spaces or special
+/// characters, and at most 100 characters long.
+fn sanitize_channel_name(name: &str) -> String {
+    let mut sanitized = String::with_capacity(name.len());
+
+    for c in name.chars().flat_map(char::to_lowercase) {
+        if c.is_alphanumeric() || c == '_' {
+            sanitized.push(c);
+        } else if !sanitized.is_empty() && !sanitized.ends_with('-') {
+            sanitized.push('-');
+        }
+    }
+
+    let sanitized: String = sanitized.chars().take(CHANNEL_NAME_MAX_LENGTH).collect();
+
+    sanitized.trim_end_matches('-').to_owned()
+}
+
+/// Post a message in the guild's staff log channel, if one is configured.
+pub async fn log_to_staff<'a>(http: impl AsRef<Http>, database: &mut MutexGuard<'a, Connection>, guild_id: GuildId, content: impl Display) -> Result<(), Error> {
+    let guild_key = format!("guild:{}", guild_id);
+    let log_channel: Option<u64> = database.hget(&guild_key, "log_channel")?;
+
+    match log_channel {
+        Some(log_channel) => {
+            ChannelId(log_channel).say(http, content).await?;
+        },
+        None => warn!("no log channel configured in guild {}, dropping staff log: {}", guild_id, content),
+    }
+
+    Ok(())
+}
+
+/// Append an entry to a member's onboarding history in a guild.
+pub fn record_history(database: &mut Connection, guild_id: GuildId, user_id: UserId, entry: &HistoryEntry) -> Result<(), Error> {
+    let history_key = format!("history:{}:{}", guild_id, user_id);
+
+    let _: () = database.rpush(&history_key, serde_json::to_string(entry)?)?;
+
+    Ok(())
+}
+
+/// Get a member's onboarding history in a guild, from oldest to newest entry.
+pub fn member_history(database: &mut Connection, guild_id: GuildId, user_id: UserId) -> Result<Vec<HistoryEntry>, Error> {
+    let history_key = format!("history:{}:{}", guild_id, user_id);
+    let entries: Vec<String> = database.lrange(&history_key, 0, -1)?;
+
+    entries.iter()
+        .map(|entry| serde_json::from_str(entry).map_err(Error::from))
+        .collect()
+}
+
+/// Lock the database for an admin command, or reply why the command can't be run.
+pub async fn admin_database<'a>(ctx: Context<'a>) -> Result<Option<MutexGuard<'a, Connection>>, Error> {
+    let bot = ctx.data();
+    let guild_id = ctx.guild_id().unwrap();
+    let serves_guild = bot.serves_guild(guild_id).await?;
+
+    if !serves_guild {
+        poise::send_reply(ctx, |reply| {
+            reply
+                .content("I am not configured to work on this server! Have an admin configure me using the /setup command.")
+                .ephemeral(true)
+        }).await?;
+
+        return Ok(None);
+    }
+
+    let guild_key = format!("guild:{}", guild_id);
+    let mut database = bot.database.lock().await;
+
+    let admin_role = database.hget(&guild_key, "admin_role")?;
+    let admin_role = RoleId(admin_role);
+
+    if ctx.author().id != ctx.guild().unwrap().owner_id &&
+        !ctx.author().has_role(&ctx, guild_id, admin_role).await? {
+        poise::send_reply(ctx, |reply| {
+            reply
+                .content("This is an admin command, you do not have the required rights to run it!")
+                .ephemeral(true)
+        }).await?;
+
+        return Ok(None);
+    }
+
+    Ok(Some(database))
+}
+
+/// Check whether the author of a command is a staff member, that is the guild owner, or a member
+/// with either the admin role or the onboarding notify role.
+pub async fn is_staff<'a>(ctx: Context<'_>, database: &mut MutexGuard<'a, Connection>, guild_id: GuildId) -> Result<bool, Error> {
+    if ctx.author().id == ctx.guild().unwrap().owner_id {
+        return Ok(true);
+    }
+
+    let admin_role = database.hget(format!("guild:{}", guild_id), "admin_role")?;
+    let notify_role: Option<u64> = database.hget(format!("onboarding:{}", guild_id), "notify_role")?;
+
+    for role in std::iter::once(admin_role).chain(notify_role) {
+        if ctx.author().has_role(&ctx, guild_id, RoleId(role)).await? {
+            return Ok(true);
+        }
+    }
+
+    Ok(false)
+}
+
+/// Check whether a member is a staff member, for interactions that don't go through commands.
+pub fn member_is_staff(cache: &Cache, database: &mut Connection, member: &Member) -> Result<bool, Error> {
+    if cache.guild_field(member.guild_id, |guild| guild.owner_id) == Some(member.user.id) {
+        return Ok(true);
+    }
+
+    let admin_role = database.hget(format!("guild:{}", member.guild_id), "admin_role")?;
+    let notify_role: Option<u64> = database.hget(format!("onboarding:{}", member.guild_id), "notify_role")?;
+
+    Ok(std::iter::once(admin_role).chain(notify_role).any(|role| member.roles.contains(&RoleId(role))))
+}
+
+/// Get the notes staff wrote about a member of a guild, from oldest to newest.
+pub fn member_notes(database: &mut Connection, guild_id: GuildId, user_id: UserId) -> Result<Vec<Note>, Error> {
+    let notes_key = format!("notes:{}:{}", guild_id, user_id);
+    let notes: Vec<String> = database.hvals(&notes_key)?;
+    let mut notes = notes.iter()
+        .map(|note| serde_json::from_str(note).map_err(Error::from))
+        .collect::<Result<Vec<Note>, Error>>()?;
+
+    notes.sort_by_key(|note| note.id);
+
+    Ok(notes)
+}
+
+/// Build an embed listing the notes staff wrote about a member, keeping the most recent ones if
+/// there are too many to fit.
+pub fn notes_embed<'a>(user: &User, notes: &[Note], embed: &'a mut CreateEmbed) -> &'a mut CreateEmbed {
+    embed
+        .colour(Colour::GOLD)
+        .title(format!("Staff notes about {}", tag(&user.name, user.discriminator)));
+
+    let mut fields = Vec::new();
+    let mut total_length = 0;
+
+    for note in notes.iter().rev().take(EMBED_MAX_FIELDS) {
+        let name = format!("#{}", note.id);
+        let signature = format!("\n— {}, <t:{}:f>", note.author.mention(), note.timestamp);
+        let value = truncate(&note.content, EMBED_FIELD_MAX_LENGTH - signature.chars().count()) + &signature;
+        let length = name.chars().count() + value.chars().count();
+
+        if total_length + length > EMBED_FIELDS_MAX_TOTAL_LENGTH {
+            break;
+        }
+
+        total_length += length;
+        fields.push((name, value));
+    }
+
+    if fields.len() < notes.len() {
+        embed.footer(|footer| footer.text(format!("{} older notes not shown", notes.len() - fields.len())));
+    }
+
+    for (name, value) in fields.into_iter().rev() {
+        embed.field(name, value, false);
+    }
+
+    embed
+}
+
+/// Count a step of the onboarding process, such as an application or an approval, towards the
+/// conversion statistics of the invite the member joined with. Each member is only counted once
+/// per step, even if they apply again after a denial or revocation.
+pub fn record_invite_conversion(database: &mut Connection, guild_id: GuildId, user_id: UserId, conversion: InviteConversion) -> Result<(), Error> {
+    let invite: Option<String> = database.hget(format!("validation:{}:{}", guild_id, user_id), "invite")?;
+
+    if let Some(invite) = invite {
+        let newly_counted: bool = database.sadd(conversion.counted_members_key(guild_id), user_id.as_u64())?;
+
+        if newly_counted {
+            let _: () = database.hincr(conversion.key(guild_id), invite, 1)?;
+        }
+    }
+
+    Ok(())
+}
+
+/// Get one of a guild's role menus or role groups by name.
+pub fn role_selection<T: RoleSelection>(database: &mut Connection, guild_id: GuildId, name: &str) -> Result<Option<T>, Error> {
+    let role_selection: Option<String> = database.hget(format!("{}:{}", T::KEY_PREFIX, guild_id), name)?;
+
+    role_selection
+        .map(|role_selection| serde_json::from_str(&role_selection).map_err(Error::from))
+        .transpose()
+}
+
+/// Get all of a guild's role menus or role groups, sorted by name.
+pub fn role_selections<T: RoleSelection>(database: &mut Connection, guild_id: GuildId) -> Result<Vec<T>, Error> {
+    let role_selections: Vec<String> = database.hvals(format!("{}:{}", T::KEY_PREFIX, guild_id))?;
+    let mut role_selections = role_selections.iter()
+        .map(|role_selection| serde_json::from_str(role_selection).map_err(Error::from))
+        .collect::<Result<Vec<T>, Error>>()?;
+
+    role_selections.sort_by(|a, b| a.name().cmp(b.name()));
+
+    Ok(role_selections)
+}
+
+/// Store one of a guild's role menus or role groups, replacing any other with the same name.
+pub fn save_role_selection<T: RoleSelection>(database: &mut Connection, guild_id: GuildId, role_selection: &T) -> Result<(), Error> {
+    let _: () = database.hset(format!("{}:{}", T::KEY_PREFIX, guild_id), role_selection.name(), serde_json::to_string(role_selection)?)?;
+
+    Ok(())
+}
+
+/// Get the pages of a guild's welcome guide. Guilds that didn't write their own pages get a
+/// default guide pointing to the rules, the introductions channel and where to pick roles.
+pub fn guide_pages(database: &mut Connection, guild_id: GuildId) -> Result<Vec<GuidePage>, Error> {
+    let pages: Vec<String> = database.lrange(format!("guide:{}", guild_id), 0, -1)?;
+
+    if !pages.is_empty() {
+        return pages.iter()
+            .map(|page| serde_json::from_str(page).map_err(Error::from))
+            .collect();
+    }
+
+    let guild_key = format!("guild:{}", guild_id);
+    let introductions_channel: u64 = database.hget(&guild_key, "introductions_channel")?;
+    let role_assignment_channel: u64 = database.hget(&guild_key, "role_assignment_channel")?;
+    let rules: Option<String> = database.hget(format!("onboarding:{}", guild_id), "rules")?;
+    let mut pages = Vec::new();
+
+    if let Some(rules) = rules {
+        pages.push(GuidePage {
+            title: "📜 Rules".to_owned(),
+            content: rules,
+        });
+    }
+
+    pages.push(GuidePage {
+        title: "🧭 Channels".to_owned(),
+        content: format!(
+            "Say hi and write a few words about yourself in {}, if you like.",
+            ChannelId(introductions_channel).mention(),
+        ),
+    });
+    pages.push(GuidePage {
+        title: "🎭 Roles".to_owned(),
+        content: format!(
+            "Pick roles matching your pronouns, interests and the notifications you want in {}.",
+            ChannelId(role_assignment_channel).mention(),
+        ),
+    });
+
+    Ok(pages)
+}
+
+/// Store the pages of a guild's welcome guide, replacing the existing ones.
+pub fn save_guide_pages(database: &mut Connection, guild_id: GuildId, pages: &[GuidePage]) -> Result<(), Error> {
+    let guide_key = format!("guide:{}", guild_id);
+
+    let _: () = database.del(&guide_key)?;
+
+    for page in pages {
+        let _: () = database.rpush(&guide_key, serde_json::to_string(page)?)?;
+    }
+
+    Ok(())
+}
+
+/// Build the embed showing a page of a guild's welcome guide.
+pub fn guide_embed<'a>(guild_name: &str, pages: &[GuidePage], page: usize, embed: &'a mut CreateEmbed) -> &'a mut CreateEmbed {
+    embed
+        .colour(Colour::BLURPLE)
+        .author(|author| author.name(format!("Guide to {}", guild_name)))
+        .footer(|footer| footer.text(format!("Page {} of {}", page + 1, pages.len())));
+
+    if let Some(guide_page) = pages.get(page) {
+        embed
+            .title(&guide_page.title)
+            .description(&guide_page.content);
+    }
+
+    embed
+}
+
+/// Build the buttons to page through a guild's welcome guide. The guild is part of the custom
+/// IDs, since guides are browsed in direct messages where interactions don't have one.
+pub fn guide_components(guild_id: GuildId, page: usize, page_count: usize, components: &mut CreateComponents) -> &mut CreateComponents {
+    components.create_action_row(|row| {
+        row
+            .create_button(|button| {
+                button
+                    .custom_id(format!("{}:{}:{}", identifiers::GUIDE, guild_id, page.saturating_sub(1)))
+                    .style(ButtonStyle::Secondary)
+                    .label("◀ Previous")
+                    .disabled(page == 0)
+            })
+            .create_button(|button| {
+                button
+                    .custom_id(format!("{}:{}:{}", identifiers::GUIDE, guild_id, page + 1))
+                    .style(ButtonStyle::Primary)
+                    .label("Next ▶")
+                    .disabled(page + 1 >= page_count)
+            })
+    })
+}
+
+/// Check that a role can be configured as one of the guild's roles, returning why it can't. Roles
+/// the bot gives and takes away must also be below its own and not managed by an integration.
+pub fn role_problem(name: &str, role: &Role, bot_position: i64, assigned: bool) -> Option<String> {
+    if role.id.0 == role.guild_id.0 {
+        Some(format!("The {} can't be @everyone.", name))
+    } else if assigned && role.managed {
+        Some(format!("The {} {} is managed by an integration, I can't give it to members.", name, role.mention()))
+    } else if assigned && role.position >= bot_position {
+        Some(format!("The {} {} is above my highest role, move my role above it so that I can give it to members.", name, role.mention()))
+    } else {
+        None
+    }
+}
+
+/// Get the position of the bot's highest role in the guild a command is run in.
+pub async fn bot_position(ctx: Context<'_>) -> Result<i64, Error> {
+    let cache = &ctx.serenity_context().cache;
+    let bot_member = ctx.guild_id().unwrap().member(&ctx, cache.current_user_id()).await?;
+
+    Ok(bot_member.highest_role_info(cache).map(|(_, position)| position).unwrap_or_default())
+}
+
+/// Check that everything a guild configured still exists and that the bot has the permissions it
+/// needs to use it, returning the problems to fix.
+pub fn configuration_problems(cache: &Cache, database: &mut Connection, guild: &Guild, bot_member: &Member) -> Result<Vec<String>, Error> {
+    let guild_key = format!("guild:{}", guild.id);
+    let onboarding_key = format!("onboarding:{}", guild.id);
+    let mut problems = Vec::new();
+
+    let guild_permissions = bot_member.permissions(cache)?;
+    let bot_position = bot_member.highest_role_info(cache).map(|(_, position)| position).unwrap_or_default();
+    let posting_permissions = Permissions::VIEW_CHANNEL | Permissions::SEND_MESSAGES | Permissions::EMBED_LINKS;
+
+    if !guild_permissions.contains(Permissions::MANAGE_ROLES) {
+        problems.push("I need the Manage Roles permission to give roles to members.".to_owned());
+    }
+
+    if !guild_permissions.contains(Permissions::KICK_MEMBERS) {
+        problems.push("I need the Kick Members permission to kick denied and inactive members.".to_owned());
+    }
+
+    if !guild_permissions.contains(Permissions::MANAGE_GUILD) {
+        problems.push("I need the Manage Server permission to track which invites members joined with.".to_owned());
+    }
+
+    // Roles the bot only checks, then roles it gives and takes away, which must be below its own
+    let mut roles = vec![("admin role", database.hget(&guild_key, "admin_role")?, false)];
+
+    for (name, field) in [("notify role", "notify_role"), ("trusted role", "trusted_role")] {
+        let role: Option<u64> = database.hget(&onboarding_key, field)?;
+
+        roles.extend(role.map(|role| (name, role, false)));
+    }
+
+    roles.push(("validated role", database.hget(&guild_key, "validated_role")?, true));
+
+    let newcomer_role: Option<u64> = database.hget(&onboarding_key, "newcomer_role")?;
+
+    roles.extend(newcomer_role.map(|role| ("newcomer role", role, true)));
+
+    for (name, role, assigned) in roles {
+        match guild.roles.get(&RoleId(role)) {
+            None => problems.push(format!("The {} (id: {}) no longer exists.", name, role)),
+            Some(role) => problems.extend(role_problem(name, role, bot_position, assigned)),
+        }
+    }
+
+    let introductions_threads: Option<bool> = database.hget(&onboarding_key, "introductions_threads")?;
+    let introductions_one_post: Option<bool> = database.hget(&onboarding_key, "introductions_one_post")?;
+    let mut introductions_permissions = Permissions::VIEW_CHANNEL | Permissions::SEND_MESSAGES;
+
+    if introductions_threads.unwrap_or_default() {
+        introductions_permissions |= Permissions::CREATE_PUBLIC_THREADS;
+    }
+
+    if introductions_one_post.unwrap_or_default() {
+        introductions_permissions |= Permissions::MANAGE_MESSAGES;
+    }
+
+    let mut channels = vec![
+        ("introductions channel", Some(database.hget(&guild_key, "introductions_channel")?), introductions_permissions),
+        ("role assignment channel", Some(database.hget(&guild_key, "role_assignment_channel")?), posting_permissions),
+        ("welcome channel", Some(database.hget(&guild_key, "welcome_channel")?), posting_permissions),
+        ("log channel", database.hget(&guild_key, "log_channel")?, posting_permissions),
+        ("rules channel", database.hget(&onboarding_key, "rules_channel")?, posting_permissions),
+    ];
+
+    let onboarding_mode: Option<String> = database.hget(&onboarding_key, "mode")?;
+    let onboarding_mode: OnboardingMode = onboarding_mode.and_then(|mode| mode.parse().ok()).unwrap_or_default();
+
+    if onboarding_mode == OnboardingMode::Thread {
+        let threads_permissions = posting_permissions | Permissions::CREATE_PRIVATE_THREADS | Permissions::MANAGE_THREADS;
+
+        channels.push(("validation threads channel", database.hget(&onboarding_key, "threads_channel")?, threads_permissions));
+    }
+
+    for (name, channel, required_permissions) in channels {
+        let channel: u64 = match channel {
+            Some(channel) => channel,
+            None => continue,
+        };
+
+        match guild.channels.get(&ChannelId(channel)) {
+            Some(Channel::Guild(channel)) if matches!(channel.kind, ChannelType::Text | ChannelType::News) => {
+                let missing_permissions = required_permissions - guild.user_permissions_in(channel, bot_member)?;
+
+                if !missing_permissions.is_empty() {
+                    problems.push(format!("I am missing the {} permissions in the {} {}.", missing_permissions, name, channel.mention()));
+                }
+            },
+            Some(channel) => problems.push(format!("The {} {} is not a text channel.", name, channel.mention())),
+            None => problems.push(format!("The {} (id: {}) no longer exists.", name, channel)),
+        }
+    }
+
+    let validation_category: u64 = database.hget(&guild_key, "validation_category")?;
+
+    match guild.channels.get(&ChannelId(validation_category)) {
+        Some(Channel::Category(category)) => {
+            let required_permissions = posting_permissions | Permissions::MANAGE_CHANNELS | Permissions::MANAGE_ROLES;
+            let missing_permissions = required_permissions - category_permissions(guild, category, bot_member, guild_permissions);
+
+            if !missing_permissions.is_empty() {
+                problems.push(format!("I am missing the {} permissions in the validation category {}, to create validation channels.", missing_permissions, category.id.mention()));
+            }
+        },
+        Some(channel) => problems.push(format!("The validation category {} is not a category.", channel.mention())),
+        None => problems.push(format!("The validation category (id: {}) no longer exists.", validation_category)),
+    }
+
+    Ok(problems)
+}
+
+/// Build an embed reporting the problems found in a guild's configuration.
+pub fn configuration_problems_embed<'a>(problems: &[String], embed: &'a mut CreateEmbed) -> &'a mut CreateEmbed {
+    if problems.is_empty() {
+        return embed
+            .colour(Colour::BLURPLE)
+            .title("🩺 Configuration check")
+            .description("✅ Everything is configured correctly and I have all the permissions I need.");
+    }
+
+    embed
+        .colour(Colour::ORANGE)
+        .title("🩺 Configuration check")
+        .description(problems.iter().map(|problem| format!("⚠️ {}", problem)).collect::<Vec<_>>().join("\n"))
+}
+
+/// Compute a member's permissions in a category, applying its permission overwrites the way
+/// Discord does for regular channels.
+fn category_permissions(guild: &Guild, category: &ChannelCategory, member: &Member, guild_permissions: Permissions) -> Permissions {
+    if member.user.id == guild.owner_id || guild_permissions.contains(Permissions::ADMINISTRATOR) {
+        return Permissions::all();
+    }
+
+    let mut permissions = guild_permissions;
+    let mut roles_deny = Permissions::empty();
+    let mut roles_allow = Permissions::empty();
+
+    for overwrite in &category.permission_overwrites {
+        match overwrite.kind {
+            PermissionOverwriteType::Role(role) if role.0 == guild.id.0 => permissions = (permissions & !overwrite.deny) | overwrite.allow,
+            PermissionOverwriteType::Role(role) if member.roles.contains(&role) => {
+                roles_deny |= overwrite.deny;
+                roles_allow |= overwrite.allow;
+            },
+            _ => (),
+        }
+    }
+
+    permissions = (permissions & !roles_deny) | roles_allow;
+
+    for overwrite in &category.permission_overwrites {
+        if overwrite.kind == PermissionOverwriteType::Member(member.user.id) {
+            permissions = (permissions & !overwrite.deny) | overwrite.allow;
+        }
+    }
+
+    permissions
+}